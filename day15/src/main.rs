@@ -1,3 +1,8 @@
+const MODULUS: u64 = 2147483647;
+// The prime factors of MODULUS - 1, which any multiplicative order divides.
+const ORDER_FACTORS: [u64; 7] = [2, 3, 7, 11, 31, 151, 331];
+
+#[derive(Clone)]
 struct Generator {
     curr: u64,
     factor: u64,
@@ -8,8 +13,8 @@ impl Generator {
     fn new(start: u64, factor: u64, multiple: u64) -> Generator {
         Generator {
             curr: start,
-            factor: factor,
-            multiple: multiple,
+            factor,
+            multiple,
         }
     }
 }
@@ -19,8 +24,8 @@ impl Iterator for Generator {
 
     fn next(&mut self) -> Option<u64> {
         loop {
-            self.curr = self.curr * self.factor % 2147483647;
-            if self.curr % self.multiple == 0 {
+            self.curr = self.curr * self.factor % MODULUS;
+            if self.curr.is_multiple_of(self.multiple) {
                 return Some(self.curr);
             }
         }
@@ -34,6 +39,183 @@ fn judge_generators(a: Generator, b: Generator, iterations: usize) -> u64 {
         .count() as u64
 }
 
+const MASK: u64 = 0xFFFF;
+const BUCKETS: usize = (MASK + 1) as usize;
+
+// The statistics are `None` when there are too few samples for them to mean
+// anything.
+struct Report {
+    samples: usize,
+    chi_square: Option<(f64, f64)>,
+    serial_correlation: f64,
+    runs_z: Option<f64>,
+    period: Option<u64>,
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "samples:            {}", self.samples)?;
+        match self.chi_square {
+            Some((chi, z)) => writeln!(
+                f,
+                "chi-square:         {:.2} ({} dof, z = {:.3})",
+                chi,
+                BUCKETS - 1,
+                z
+            )?,
+            None => writeln!(f, "chi-square:         too few samples")?,
+        }
+        writeln!(f, "serial correlation: {:.6}", self.serial_correlation)?;
+        match self.runs_z {
+            Some(z) => writeln!(f, "runs test:          z = {:.3}", z)?,
+            None => writeln!(f, "runs test:          too few samples")?,
+        }
+        match self.period {
+            Some(period) => write!(f, "period:             {}", period),
+            None => write!(f, "period:             not found"),
+        }
+    }
+}
+
+// Chi-square of the masked values against a uniform distribution over all
+// 2^16 buckets, along with its normal approximation. The approximation needs
+// at least 5 values expected in each bucket.
+fn chi_square(values: &[u64]) -> Option<(f64, f64)> {
+    let expected = values.len() as f64 / BUCKETS as f64;
+    if expected < 5.0 {
+        return None;
+    }
+    let mut counts = vec![0u64; BUCKETS];
+    for &value in values {
+        counts[value as usize] += 1;
+    }
+    let chi = counts
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum::<f64>();
+    let dof = (BUCKETS - 1) as f64;
+    Some((chi, (chi - dof) / (2.0 * dof).sqrt()))
+}
+
+// Lag-1 circular serial correlation coefficient.
+fn serial_correlation(values: &[u64]) -> f64 {
+    let n = values.len() as f64;
+    let (sum, sum_sq, sum_lag) = values.iter().zip(values.iter().cycle().skip(1)).fold(
+        (0.0, 0.0, 0.0),
+        |(sum, sum_sq, sum_lag), (&u, &v)| {
+            let (u, v) = (u as f64, v as f64);
+            (sum + u, sum_sq + u * u, sum_lag + u * v)
+        },
+    );
+    let denominator = n * sum_sq - sum * sum;
+    if denominator == 0.0 {
+        return 1.0;
+    }
+    (n * sum_lag - sum * sum) / denominator
+}
+
+// Wald-Wolfowitz runs test above and below the median of the masked range,
+// which needs values on both sides of it.
+fn runs_z(values: &[u64]) -> Option<f64> {
+    let median = BUCKETS as u64 / 2;
+    let above = values.iter().filter(|&&value| value >= median).count() as f64;
+    let below = values.len() as f64 - above;
+    let runs = 1 + values
+        .windows(2)
+        .filter(|pair| (pair[0] >= median) != (pair[1] >= median))
+        .count();
+    let n = above + below;
+    if above == 0.0 || below == 0.0 {
+        return None;
+    }
+    let mean = 2.0 * above * below / n + 1.0;
+    let variance = 2.0 * above * below * (2.0 * above * below - n) / (n * n * (n - 1.0));
+    if variance <= 0.0 {
+        return None;
+    }
+    Some((runs as f64 - mean) / variance.sqrt())
+}
+
+// Brent's cycle detection over the generator's outputs, giving up after
+// `limit` steps.
+fn find_period(gen: Generator, limit: u64) -> Option<u64> {
+    let mut gen = gen;
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = gen.next()?;
+    let mut hare = gen.next()?;
+    let mut steps = 1;
+    while tortoise != hare {
+        if steps >= limit {
+            return None;
+        }
+        if power == period {
+            tortoise = hare;
+            power *= 2;
+            period = 0;
+        }
+        hare = gen.next()?;
+        period += 1;
+        steps += 1;
+    }
+    Some(period)
+}
+
+fn pow_mod(base: u64, exponent: u64) -> u64 {
+    let (mut base, mut exponent, mut result) = (base % MODULUS, exponent, 1);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % MODULUS;
+        }
+        base = base * base % MODULUS;
+        exponent >>= 1;
+    }
+    result
+}
+
+// The smallest k > 0 with factor^k = 1 mod MODULUS, found by dividing the
+// group order MODULUS - 1 by its prime factors while that still holds.
+fn multiplicative_order(factor: u64) -> Option<u64> {
+    if factor.is_multiple_of(MODULUS) {
+        return None;
+    }
+    let mut order = MODULUS - 1;
+    for &q in &ORDER_FACTORS {
+        while order.is_multiple_of(q) && pow_mod(factor, order / q) == 1 {
+            order /= q;
+        }
+    }
+    Some(order)
+}
+
+// The period of the generator's outputs. When it keeps every value that is
+// the multiplicative order of its factor, or 1 once it reaches 0. Otherwise
+// Brent's algorithm runs for long enough to go round the whole cycle.
+fn period(gen: &Generator) -> Option<u64> {
+    if gen.multiple == 1 {
+        if gen.curr.is_multiple_of(MODULUS) {
+            return Some(1);
+        }
+        return multiplicative_order(gen.factor).or(Some(1));
+    }
+    find_period(gen.clone(), 4 * MODULUS)
+}
+
+fn analyze_generator(gen: Generator, samples: usize) -> Report {
+    let values = gen
+        .clone()
+        .take(samples)
+        .map(|value| value & MASK)
+        .collect::<Vec<_>>();
+    Report {
+        samples,
+        chi_square: chi_square(&values),
+        serial_correlation: serial_correlation(&values),
+        runs_z: runs_z(&values),
+        period: period(&gen),
+    }
+}
+
 fn main() {
     let result = judge_generators(
         Generator::new(873, 16807, 1),
@@ -48,6 +230,17 @@ fn main() {
         5_000_000,
     );
     println!("result 2: {}", result);
+
+    println!("generator A:");
+    println!(
+        "{}",
+        analyze_generator(Generator::new(873, 16807, 1), 1_000_000)
+    );
+    println!("generator B:");
+    println!(
+        "{}",
+        analyze_generator(Generator::new(583, 48271, 1), 1_000_000)
+    );
 }
 
 #[test]
@@ -69,3 +262,56 @@ fn judge_generators_2_test() {
     );
     assert_eq!(result, 309);
 }
+
+#[test]
+fn find_period_test() {
+    assert_eq!(find_period(Generator::new(65, 1, 1), 10), Some(1));
+    assert_eq!(find_period(Generator::new(65, 2147483646, 1), 10), Some(2));
+    assert_eq!(find_period(Generator::new(65, 16807, 1), 1_000), None);
+}
+
+#[test]
+fn multiplicative_order_test() {
+    // Both puzzle factors are primitive roots, going through every value.
+    assert_eq!(multiplicative_order(16807), Some(MODULUS - 1));
+    assert_eq!(multiplicative_order(48271), Some(MODULUS - 1));
+    assert_eq!(multiplicative_order(1), Some(1));
+    assert_eq!(multiplicative_order(MODULUS - 1), Some(2));
+    // 16807^2 generates the squares, half of them.
+    assert_eq!(
+        multiplicative_order(16807 * 16807 % MODULUS),
+        Some((MODULUS - 1) / 2)
+    );
+    assert_eq!(multiplicative_order(MODULUS), None);
+    for factor in 2..50 {
+        let order = multiplicative_order(factor).unwrap();
+        assert_eq!(pow_mod(factor, order), 1);
+        assert!(ORDER_FACTORS
+            .iter()
+            .filter(|&&q| order.is_multiple_of(q))
+            .all(|&q| pow_mod(factor, order / q) != 1));
+    }
+}
+
+#[test]
+fn analyze_generator_test() {
+    let report = analyze_generator(Generator::new(65, 16807, 1), 1_000_000);
+    assert!(report.chi_square.unwrap().1.abs() < 5.0);
+    assert!(report.serial_correlation.abs() < 0.01);
+    assert!(report.runs_z.unwrap().abs() < 5.0);
+    assert_eq!(report.period, Some(MODULUS - 1));
+
+    let report = analyze_generator(Generator::new(65, 1, 1), 1_000);
+    assert_eq!(report.serial_correlation, 1.0);
+    assert_eq!(report.chi_square, None);
+    assert_eq!(report.runs_z, None);
+    assert_eq!(report.period, Some(1));
+
+    assert_eq!(runs_z(&[]), None);
+    assert_eq!(runs_z(&[0, 40_000]), None);
+    assert!(runs_z(&[0, 40_000, 0, 40_000, 1]).is_some());
+    assert_eq!(
+        period(&Generator::new(65, MODULUS - 1, 2)),
+        find_period(Generator::new(65, MODULUS - 1, 2), 100)
+    );
+}
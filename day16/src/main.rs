@@ -4,27 +4,27 @@ extern crate failure;
 extern crate lazy_static;
 extern crate regex;
 
-use std::collections::HashMap;
 use std::collections::VecDeque;
-use std::iter::FromIterator;
+use std::fmt;
 use std::str::FromStr;
 
 use failure::Error;
 use regex::Regex;
 
+#[derive(Clone, Debug, PartialEq)]
 enum DanceMove {
     Spin(usize),
     Exchange(usize, usize),
-    Partner(char, char),
+    Partner(String, String),
 }
 
 impl FromStr for DanceMove {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref SPIN_RE: Regex = Regex::new(r"s(?P<spins>\d+)").unwrap();
-            static ref EXCHANGE_RE: Regex = Regex::new(r"x(?P<A>\d+)/(?P<B>\d+)").unwrap();
-            static ref PARTNER_RE: Regex = Regex::new(r"p(?P<A>[a-p])/(?P<B>[a-p])").unwrap();
+            static ref SPIN_RE: Regex = Regex::new(r"^s(?P<spins>\d+)$").unwrap();
+            static ref EXCHANGE_RE: Regex = Regex::new(r"^x(?P<A>\d+)/(?P<B>\d+)$").unwrap();
+            static ref PARTNER_RE: Regex = Regex::new(r"^p(?P<A>[^/\s]+)/(?P<B>[^/\s]+)$").unwrap();
         }
 
         if let Some(caps) = SPIN_RE.captures(s) {
@@ -33,8 +33,8 @@ impl FromStr for DanceMove {
             Ok(DanceMove::Exchange(caps["A"].parse()?, caps["B"].parse()?))
         } else if let Some(caps) = PARTNER_RE.captures(s) {
             Ok(DanceMove::Partner(
-                caps["A"].chars().nth(0).unwrap(),
-                caps["B"].chars().nth(0).unwrap(),
+                caps["A"].to_string(),
+                caps["B"].to_string(),
             ))
         } else {
             Err(format_err!("Invalid move: {}", s))
@@ -42,43 +42,111 @@ impl FromStr for DanceMove {
     }
 }
 
-struct DanceTeam(VecDeque<char>);
+impl fmt::Display for DanceMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DanceMove::Spin(spins) => write!(f, "s{}", spins),
+            DanceMove::Exchange(a, b) => write!(f, "x{}/{}", a, b),
+            DanceMove::Partner(ref a, ref b) => write!(f, "p{}/{}", a, b),
+        }
+    }
+}
 
-impl ToString for DanceTeam {
-    fn to_string(&self) -> String {
-        self.0.iter().collect()
+impl DanceMove {
+    fn validate(&self, dance_team: &DanceTeam) -> Result<(), Error> {
+        match *self {
+            DanceMove::Spin(_) => Ok(()),
+            DanceMove::Exchange(a, b) => {
+                let size = dance_team.0.len();
+                match (a < size, b < size) {
+                    (true, true) => Ok(()),
+                    (false, _) => Err(format_err!("Exchange index out of range: {}", a)),
+                    (_, false) => Err(format_err!("Exchange index out of range: {}", b)),
+                }
+            }
+            DanceMove::Partner(ref a, ref b) => {
+                dance_team.position(a)?;
+                dance_team.position(b)?;
+                Ok(())
+            }
+        }
     }
 }
 
-impl FromIterator<char> for DanceTeam {
-    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
-        DanceTeam(VecDeque::from_iter(iter))
+// Members are single letters in the puzzle, but any whitespace separated
+// names work too.
+#[derive(Clone, Debug, PartialEq)]
+struct DanceTeam(VecDeque<String>);
+
+impl fmt::Display for DanceTeam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if self.0.iter().all(|m| m.chars().count() == 1) {
+            ""
+        } else {
+            " "
+        };
+        write!(
+            f,
+            "{}",
+            self.0.iter().cloned().collect::<Vec<_>>().join(separator)
+        )
+    }
+}
+
+impl FromStr for DanceTeam {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let members = if s.contains(char::is_whitespace) {
+            s.split_whitespace()
+                .map(str::to_string)
+                .collect::<VecDeque<_>>()
+        } else {
+            s.chars().map(|c| c.to_string()).collect::<VecDeque<_>>()
+        };
+        if members.is_empty() {
+            bail!("Empty dance team");
+        }
+        if let Some(member) = members
+            .iter()
+            .enumerate()
+            .find(|&(i, m)| members.iter().skip(i + 1).any(|other| other == m))
+            .map(|(_, m)| m)
+        {
+            bail!("Duplicate member: {}", member);
+        }
+        Ok(DanceTeam(members))
     }
 }
 
 impl DanceTeam {
+    fn position(&self, member: &str) -> Result<usize, Error> {
+        self.0
+            .iter()
+            .position(|m| m == member)
+            .ok_or_else(|| format_err!("Unknown member: {}", member))
+    }
+
     fn spin(&mut self, spins: usize) {
-        (0..spins).for_each(|_| {
-            let back = self.0.pop_back().unwrap();
-            self.0.push_front(back);
-        });
+        let spins = spins % self.0.len();
+        self.0.rotate_right(spins);
     }
 
     fn exchange(&mut self, a: usize, b: usize) {
         self.0.swap(a, b);
     }
 
-    fn partner(&mut self, a: char, b: char) {
-        let a = self.0.iter().position(|c| *c == a).unwrap();
-        let b = self.0.iter().position(|c| *c == b).unwrap();
+    fn partner(&mut self, a: &str, b: &str) {
+        let a = self.position(a).unwrap();
+        let b = self.position(b).unwrap();
         self.0.swap(a, b);
     }
 
     fn perform(&mut self, dance_move: &DanceMove) {
-        match dance_move {
-            &DanceMove::Spin(spins) => self.spin(spins),
-            &DanceMove::Exchange(a, b) => self.exchange(a, b),
-            &DanceMove::Partner(a, b) => self.partner(a, b),
+        match *dance_move {
+            DanceMove::Spin(spins) => self.spin(spins),
+            DanceMove::Exchange(a, b) => self.exchange(a, b),
+            DanceMove::Partner(ref a, ref b) => self.partner(a, b),
         }
     }
 
@@ -87,56 +155,352 @@ impl DanceTeam {
             .iter()
             .for_each(|dance_move| self.perform(dance_move));
     }
+
+    fn perform_compiled(&mut self, dance: &Dance) {
+        let team = self
+            .0
+            .iter()
+            .map(|m| dance.members.position(m).unwrap())
+            .collect::<Vec<_>>();
+        self.0 = dance
+            .positions
+            .0
+            .iter()
+            .map(|&i| dance.members.0[dance.labels.0[team[i]]].clone())
+            .collect();
+    }
+}
+
+// A permutation of team slots or labels, where entry `i` holds the index that
+// ends up at `i`.
+#[derive(Clone, Debug, PartialEq)]
+struct Permutation(Vec<usize>);
+
+impl Permutation {
+    fn identity(size: usize) -> Permutation {
+        Permutation((0..size).collect())
+    }
+
+    fn then(&self, other: &Permutation) -> Permutation {
+        Permutation(other.0.iter().map(|&i| self.0[i]).collect())
+    }
+
+    fn pow(&self, mut exponent: u64) -> Permutation {
+        let mut result = Permutation::identity(self.0.len());
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.then(&base);
+            }
+            base = base.then(&base);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    fn inverse(&self) -> Permutation {
+        let mut inverse = vec![0; self.0.len()];
+        for (i, &j) in self.0.iter().enumerate() {
+            inverse[j] = i;
+        }
+        Permutation(inverse)
+    }
+
+    fn cycles(&self) -> usize {
+        let mut seen = vec![false; self.0.len()];
+        let mut cycles = 0;
+        for start in 0..self.0.len() {
+            if seen[start] {
+                continue;
+            }
+            cycles += 1;
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                i = self.0[i];
+            }
+        }
+        cycles
+    }
+}
+
+// Spin and Exchange only ever move slots around while Partner only ever
+// relabels members, so a dance splits into two independent permutations.
+#[derive(Clone, Debug, PartialEq)]
+struct Dance {
+    members: DanceTeam,
+    positions: Permutation,
+    labels: Permutation,
 }
 
-fn find_pattern(
-    dance_team: &mut DanceTeam,
-    dance_moves: &[DanceMove],
-    max_iters: u64,
-) -> Option<(u64, u64)> {
-    let mut result_map = HashMap::new();
-    for iter in 0..max_iters {
-        result_map.insert(dance_team.to_string(), iter);
-        dance_team.perform_dance(dance_moves);
-        if let Some(&first) = result_map.get(&dance_team.to_string()) {
-            return Some((first, iter + 1 - first));
+impl Dance {
+    fn new(dance_team: &DanceTeam, dance_moves: &[DanceMove]) -> Dance {
+        let members = dance_team.clone();
+        let size = members.0.len();
+        let mut positions = (0..size).collect::<VecDeque<_>>();
+        let mut labels = (0..size).collect::<Vec<_>>();
+        for dance_move in dance_moves {
+            match *dance_move {
+                DanceMove::Spin(spins) => positions.rotate_right(spins % size),
+                DanceMove::Exchange(a, b) => positions.swap(a, b),
+                DanceMove::Partner(ref a, ref b) => {
+                    let a = members.position(a).unwrap();
+                    let b = members.position(b).unwrap();
+                    let a = labels.iter().position(|&l| l == a).unwrap();
+                    let b = labels.iter().position(|&l| l == b).unwrap();
+                    labels.swap(a, b);
+                }
+            }
+        }
+        Dance {
+            members,
+            positions: Permutation(positions.into_iter().collect()),
+            labels: Permutation(labels),
+        }
+    }
+
+    fn pow(&self, exponent: u64) -> Dance {
+        Dance {
+            members: self.members.clone(),
+            positions: self.positions.pow(exponent),
+            labels: self.labels.pow(exponent),
         }
     }
-    None
+
+    fn inverse(&self) -> Dance {
+        Dance {
+            members: self.members.clone(),
+            positions: self.positions.inverse(),
+            labels: self.labels.inverse(),
+        }
+    }
+
+    // Performing `self` and then `other`. Slots are looked up through
+    // `self` first, while labels are renamed by `self` first.
+    fn compose(&self, other: &Dance) -> Result<Dance, Error> {
+        if self.members != other.members {
+            bail!("Dances are for different teams");
+        }
+        Ok(Dance {
+            members: self.members.clone(),
+            positions: self.positions.then(&other.positions),
+            labels: other.labels.then(&self.labels),
+        })
+    }
+
+    // Any Exchange followed by a Spin can be rewritten as the Spin followed by
+    // a shifted Exchange, and Partner moves commute with both, so a shortest
+    // dance is at most one Spin, then Exchanges, then Partners. Each
+    // permutation is then sorted with one swap per element outside its cycle.
+    fn minimize(&self) -> Vec<DanceMove> {
+        let size = self.positions.0.len();
+        let exchanges = |spins: usize| {
+            Permutation(
+                self.positions
+                    .0
+                    .iter()
+                    .map(|&i| (i + spins) % size)
+                    .collect(),
+            )
+        };
+        let spins = (0..size)
+            .min_by_key(|&spins| {
+                let cost = size - exchanges(spins).cycles();
+                if spins == 0 {
+                    cost
+                } else {
+                    cost + 1
+                }
+            })
+            .unwrap_or(0);
+
+        let mut dance_moves = Vec::new();
+        if spins != 0 {
+            dance_moves.push(DanceMove::Spin(spins));
+        }
+        let mut positions = (0..size).collect::<VecDeque<_>>();
+        positions.rotate_right(spins);
+        for i in 0..size {
+            if positions[i] != self.positions.0[i] {
+                let j = positions
+                    .iter()
+                    .position(|&p| p == self.positions.0[i])
+                    .unwrap();
+                positions.swap(i, j);
+                dance_moves.push(DanceMove::Exchange(i, j));
+            }
+        }
+        let mut labels = (0..size).collect::<Vec<_>>();
+        for i in 0..size {
+            if labels[i] != self.labels.0[i] {
+                let j = labels.iter().position(|&l| l == self.labels.0[i]).unwrap();
+                dance_moves.push(DanceMove::Partner(
+                    self.members.0[labels[i]].clone(),
+                    self.members.0[labels[j]].clone(),
+                ));
+                labels.swap(i, j);
+            }
+        }
+        dance_moves
+    }
 }
 
-fn parse_input(dance: &str) -> Result<Vec<DanceMove>, Error> {
+fn parse_input(dance: &str, dance_team: &DanceTeam) -> Result<Vec<DanceMove>, Error> {
     dance
         .split(',')
-        .map(str::parse::<DanceMove>)
+        .map(|s| {
+            let dance_move = s.trim().parse::<DanceMove>()?;
+            dance_move.validate(dance_team)?;
+            Ok(dance_move)
+        })
         .collect::<Result<Vec<_>, Error>>()
 }
 
-fn perform_dance(dance_moves: &[DanceMove]) -> Result<String, Error> {
-    let mut dance_team = "abcdefghijklmnop".chars().collect::<DanceTeam>();
+fn perform_dance(dance_team: &DanceTeam, dance_moves: &[DanceMove]) -> String {
+    let mut dance_team = dance_team.clone();
     dance_team.perform_dance(dance_moves);
-    Ok(dance_team.to_string())
+    dance_team.to_string()
 }
 
-fn perform_dance_2(dance_moves: &[DanceMove]) -> Result<String, Error> {
+fn perform_dance_2(dance_team: &DanceTeam, dance_moves: &[DanceMove]) -> String {
     const DANCE_ITERS: u64 = 1_000_000_000;
-    let mut dance_team = "abcdefghijklmnop".chars().collect::<DanceTeam>();
-    let (first, reps) =
-        find_pattern(&mut dance_team, &dance_moves, DANCE_ITERS).ok_or(format_err!("No pattern"))?;
-    let remaining_iters = (DANCE_ITERS - first) % reps;
-    (0..remaining_iters).for_each(|_| dance_team.perform_dance(dance_moves));
+    let mut dance_team = dance_team.clone();
+    let dance = Dance::new(&dance_team, dance_moves).pow(DANCE_ITERS);
+    dance_team.perform_compiled(&dance);
+    dance_team.to_string()
+}
 
-    Ok(dance_team.to_string())
+fn format_dance(dance_moves: &[DanceMove]) -> String {
+    dance_moves
+        .iter()
+        .map(|dance_move| dance_move.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 fn main() {
     let input = include_str!("input.txt");
     let input = input.trim();
-    let dance_moves = parse_input(input).expect("parse");
+    let dance_team = "abcdefghijklmnop".parse::<DanceTeam>().expect("team");
+    let dance_moves = parse_input(input, &dance_team).expect("parse");
 
-    let order = perform_dance(&dance_moves).expect("parse");
+    let order = perform_dance(&dance_team, &dance_moves);
     println!("Result 1: {}", order);
 
-    let order = perform_dance_2(&dance_moves).expect("parse");
+    let order = perform_dance_2(&dance_team, &dance_moves);
     println!("Result 2: {}", order);
+
+    let dance = Dance::new(&dance_team, &dance_moves);
+    println!("Minimized dance: {}", format_dance(&dance.minimize()));
+    println!(
+        "Minimized inverse: {}",
+        format_dance(&dance.inverse().minimize())
+    );
+    let twice = dance.compose(&dance).expect("compose");
+    println!(
+        "Minimized double dance: {}",
+        format_dance(&twice.minimize())
+    );
+}
+
+#[test]
+fn perform_compiled_test() {
+    let mut dance_team = "abcde".parse::<DanceTeam>().unwrap();
+    let dance_moves = parse_input("s1,x3/4,pe/b", &dance_team).unwrap();
+    let dance = Dance::new(&dance_team, &dance_moves);
+    dance_team.perform_compiled(&dance);
+    assert_eq!(dance_team.to_string(), "baedc");
+    dance_team.perform_compiled(&dance);
+    assert_eq!(dance_team.to_string(), "ceadb");
+
+    let mut dance_team = "abcde".parse::<DanceTeam>().unwrap();
+    dance_team.perform_compiled(&dance.pow(2));
+    assert_eq!(dance_team.to_string(), "ceadb");
+}
+
+#[test]
+fn dance_pow_test() {
+    let mut expected = "abcdefgh".parse::<DanceTeam>().unwrap();
+    let dance_moves = parse_input("s3,x0/7,pa/c,x2/5,s1,pd/h,pb/a,x1/6", &expected).unwrap();
+    let dance = Dance::new(&expected, &dance_moves);
+    for iters in 0..50 {
+        let mut dance_team = "abcdefgh".parse::<DanceTeam>().unwrap();
+        dance_team.perform_compiled(&dance.pow(iters));
+        assert_eq!(dance_team.to_string(), expected.to_string());
+        expected.perform_dance(&dance_moves);
+    }
+}
+
+#[test]
+fn named_dance_team_test() {
+    let dance_team = "alice bob carol dave".parse::<DanceTeam>().unwrap();
+    let dance_moves = parse_input("s1,x0/3,palice/bob", &dance_team).unwrap();
+    assert_eq!(
+        perform_dance(&dance_team, &dance_moves),
+        "carol bob alice dave"
+    );
+    assert_eq!(
+        perform_dance_2(&dance_team, &dance_moves),
+        "carol alice bob dave"
+    );
+}
+
+#[test]
+fn parse_input_errors_test() {
+    let dance_team = "abcde".parse::<DanceTeam>().unwrap();
+    assert!(parse_input("s1,x3/5", &dance_team).is_err());
+    assert!(parse_input("pa/z", &dance_team).is_err());
+    assert!(parse_input("pa/bc", &dance_team).is_err());
+    assert!(parse_input("q1", &dance_team).is_err());
+    assert!("abca".parse::<DanceTeam>().is_err());
+    assert!("".parse::<DanceTeam>().is_err());
+}
+
+#[test]
+fn dance_inverse_compose_test() {
+    let dance_team = "abcdefgh".parse::<DanceTeam>().unwrap();
+    let first = parse_input("s3,x0/7,pa/c,x2/5", &dance_team).unwrap();
+    let second = parse_input("s1,pd/h,pb/a,x1/6", &dance_team).unwrap();
+    let both = first
+        .iter()
+        .chain(second.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    let first = Dance::new(&dance_team, &first);
+    let second = Dance::new(&dance_team, &second);
+    let both = Dance::new(&dance_team, &both);
+
+    assert_eq!(first.compose(&second).unwrap(), both);
+    assert_ne!(second.compose(&first).unwrap(), both);
+    assert_eq!(
+        both.compose(&both.inverse()).unwrap(),
+        Dance::new(&dance_team, &[])
+    );
+    assert_eq!(
+        both.inverse().compose(&both).unwrap(),
+        Dance::new(&dance_team, &[])
+    );
+
+    let other_team = "abcdefgi".parse::<DanceTeam>().unwrap();
+    assert!(both.compose(&Dance::new(&other_team, &[])).is_err());
+}
+
+#[test]
+fn dance_minimize_test() {
+    let dance_team = "abcde".parse::<DanceTeam>().unwrap();
+    let dance_moves = parse_input("s1,x3/4,pe/b,s2,s2,pb/e,x1/2,x1/2", &dance_team).unwrap();
+    let dance = Dance::new(&dance_team, &dance_moves);
+    let minimized = dance.minimize();
+    assert_eq!(minimized, vec![DanceMove::Exchange(2, 3)]);
+    assert_eq!(Dance::new(&dance_team, &minimized), dance);
+
+    let dance_moves = parse_input("s1,s4,pa/b,pb/a", &dance_team).unwrap();
+    assert!(Dance::new(&dance_team, &dance_moves).minimize().is_empty());
+
+    let dance_team = "abcdefgh".parse::<DanceTeam>().unwrap();
+    let dance_moves = parse_input("s3,x0/7,pa/c,x2/5,s1,pd/h,pb/a,x1/6", &dance_team).unwrap();
+    let dance = Dance::new(&dance_team, &dance_moves);
+    let minimized = dance.minimize();
+    assert!(minimized.len() <= dance_moves.len());
+    assert_eq!(Dance::new(&dance_team, &minimized), dance);
 }
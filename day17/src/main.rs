@@ -1,49 +1,356 @@
-use std::collections::VecDeque;
+// Bitset of the slots of the final ring not yet claimed by a value, with a
+// Fenwick tree counting the free slots per block of words. The tree is small
+// enough to stay in cache even for tens of millions of slots.
+const BLOCK_WORDS: usize = 8;
 
-fn find_value(steps: usize, iterations: usize, value: usize) -> usize {
-    let mut spinlock = VecDeque::with_capacity(iterations);
-    spinlock.push_back(0);
-    (1..iterations)
-        .scan(0, |prev, i| {
-            *prev = ((*prev + steps) % i) + 1;
-            Some((*prev, i))
+struct FreeSlots {
+    words: Vec<u64>,
+    tree: Vec<u32>,
+    top_bit: usize,
+}
+
+impl FreeSlots {
+    fn new(len: usize) -> FreeSlots {
+        let blocks = len.div_ceil(64 * BLOCK_WORDS);
+        let mut words = vec![0u64; blocks * BLOCK_WORDS];
+        words[..len / 64].iter_mut().for_each(|word| *word = !0);
+        if !len.is_multiple_of(64) {
+            words[len / 64] = (1 << (len % 64)) - 1;
+        }
+        let mut tree = vec![0; blocks + 1];
+        for i in 1..tree.len() {
+            tree[i] += words[(i - 1) * BLOCK_WORDS..i * BLOCK_WORDS]
+                .iter()
+                .map(|word| word.count_ones())
+                .sum::<u32>();
+            let parent = i + (i & i.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] += tree[i];
+            }
+        }
+        let mut top_bit = 1;
+        while top_bit * 2 < tree.len() {
+            top_bit *= 2;
+        }
+        FreeSlots {
+            words,
+            tree,
+            top_bit,
+        }
+    }
+
+    // Claims and returns the `k`th (0 based) free slot.
+    fn take(&mut self, k: usize) -> usize {
+        let mut remaining = k as u32;
+        let mut block = 0;
+        let mut bit = self.top_bit;
+        while bit > 0 {
+            let next = block + bit;
+            if next < self.tree.len() && self.tree[next] <= remaining {
+                block = next;
+                remaining -= self.tree[next];
+            }
+            bit /= 2;
+        }
+
+        let mut word = block * BLOCK_WORDS;
+        while self.words[word].count_ones() <= remaining {
+            remaining -= self.words[word].count_ones();
+            word += 1;
+        }
+        let offset = select(self.words[word], remaining);
+        self.words[word] &= !(1 << offset);
+
+        let mut i = block + 1;
+        while i < self.tree.len() {
+            self.tree[i] -= 1;
+            i += i & i.wrapping_neg();
+        }
+        word * 64 + offset
+    }
+}
+
+// Position of the `k`th (0 based) set bit.
+fn select(bits: u64, k: u32) -> usize {
+    let (mut bits, mut k, mut offset) = (bits, k, 0);
+    for &width in &[32, 16, 8] {
+        let low = (bits & ((1 << width) - 1)).count_ones();
+        if k >= low {
+            k -= low;
+            bits >>= width;
+            offset += width;
+        }
+    }
+    (0..k).for_each(|_| bits &= bits - 1);
+    offset + bits.trailing_zeros() as usize
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Insertion {
+    step: usize,
+    position: usize,
+    value: usize,
+}
+
+// Walks the spinlock, yielding every insertion without storing the ring.
+#[derive(Clone, Debug)]
+struct Spinlock {
+    steps: usize,
+    cursor: usize,
+    len: usize,
+}
+
+impl Spinlock {
+    fn new(steps: usize) -> Spinlock {
+        Spinlock {
+            steps,
+            cursor: 0,
+            len: 1,
+        }
+    }
+
+    fn last_insertion(&self) -> Option<Insertion> {
+        if self.len == 1 {
+            return None;
+        }
+        Some(Insertion {
+            step: self.len - 1,
+            position: self.cursor,
+            value: self.len - 1,
         })
-        .for_each(|(pos, i)| spinlock.insert(pos, i));
-    spinlock[(spinlock.iter().position(|&n| n == value).unwrap() + 1) % spinlock.len()]
+    }
+
+    // Steps back to before the last insertion and returns it.
+    fn undo(&mut self) -> Option<Insertion> {
+        let insertion = self.last_insertion()?;
+        let i = self.len - 1;
+        // Undo `cursor = (prev + steps) % i + 1`.
+        self.cursor = (self.cursor - 1 + i - self.steps % i) % i;
+        self.len -= 1;
+        Some(insertion)
+    }
+
+    fn ring(&self) -> SpinlockRing {
+        SpinlockRing::from_spinlock(self)
+    }
+}
+
+impl Iterator for Spinlock {
+    type Item = Insertion;
+
+    fn next(&mut self) -> Option<Insertion> {
+        let i = self.len;
+        self.cursor = ((self.cursor + self.steps) % i) + 1;
+        self.len += 1;
+        self.last_insertion()
+    }
+}
+
+// The state of a spinlock. Insert positions only depend on the step count, so
+// they are replayed backwards and each value claims its slot among those not
+// taken by later values, keeping every insert O(log n).
+struct SpinlockRing {
+    ring: Vec<u32>,
+    positions: Vec<u32>,
+    cursor: usize,
+}
+
+impl SpinlockRing {
+    fn new(steps: usize, iterations: usize) -> SpinlockRing {
+        let mut spinlock = Spinlock::new(steps);
+        spinlock.by_ref().take(iterations.max(1) - 1).for_each(drop);
+        spinlock.ring()
+    }
+
+    fn from_spinlock(spinlock: &Spinlock) -> SpinlockRing {
+        let mut spinlock = spinlock.clone();
+        let cursor = spinlock.cursor;
+        let mut ring = vec![0; spinlock.len];
+        let mut free_slots = FreeSlots::new(spinlock.len);
+        while let Some(insertion) = spinlock.undo() {
+            ring[free_slots.take(insertion.position)] = insertion.value as u32;
+        }
+        ring[free_slots.take(0)] = 0;
+
+        let mut positions = vec![0; ring.len()];
+        for (pos, &value) in ring.iter().enumerate() {
+            positions[value as usize] = pos as u32;
+        }
+        SpinlockRing {
+            ring,
+            positions,
+            cursor,
+        }
+    }
+
+    fn value_at(&self, pos: usize) -> usize {
+        self.ring[pos % self.ring.len()] as usize
+    }
+
+    fn position_of(&self, value: usize) -> Option<usize> {
+        self.positions.get(value).map(|&pos| pos as usize)
+    }
+
+    fn value_after(&self, value: usize) -> Option<usize> {
+        self.position_of(value).map(|pos| self.value_at(pos + 1))
+    }
+
+    // Renders up to `radius` values either side of the cursor in the puzzle's
+    // format, or the whole ring starting from position 0 when it fits.
+    fn render(&self, radius: usize) -> String {
+        let len = self.ring.len();
+        let positions = if 2 * radius + 1 >= len {
+            (0..len).collect::<Vec<_>>()
+        } else {
+            (0..2 * radius + 1)
+                .map(|offset| (self.cursor + len + offset - radius) % len)
+                .collect()
+        };
+        positions
+            .into_iter()
+            .map(|pos| {
+                if pos == self.cursor {
+                    format!("({})", self.ring[pos])
+                } else {
+                    format!(" {} ", self.ring[pos])
+                }
+            })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+fn find_value(steps: usize, iterations: usize, value: usize) -> usize {
+    SpinlockRing::new(steps, iterations)
+        .value_after(value)
+        .unwrap()
 }
 
+// 0 never moves from position 0, so the value after it is whichever was last
+// inserted at position 1.
 fn find_zero(steps: usize, iterations: usize) -> usize {
-    let mut zero_idx = 0;
-    let mut expected_value = 0;
-    (1..iterations)
-        .scan(0, |prev, i| {
-            *prev = ((*prev + steps) % i) + 1;
-            Some((*prev, i))
-        })
-        .for_each(|(pos, i)| {
-            if pos - 1 < zero_idx {
-                zero_idx += 1;
-            } else if pos - 1 == zero_idx {
-                expected_value = i;
-            }
-        });
-    expected_value
+    Spinlock::new(steps)
+        .take(iterations.max(1) - 1)
+        .filter(|insertion| insertion.position == 1)
+        .last()
+        .map_or(0, |insertion| insertion.value)
 }
 
 fn main() {
-    let val = create_spin_lock(386, 2018, 2017);
+    let val = find_value(386, 2018, 2017);
     println!("Result 1: {}", val);
+    println!("{}", SpinlockRing::new(386, 2018).render(3));
 
     let val = find_zero(386, 50_000_000);
     println!("Result 2: {}", val);
+
+    let spinlock = SpinlockRing::new(386, 50_000_000);
+    println!(
+        "After 50M: value after 0 is {}, value at position 0 is {}",
+        spinlock.value_after(0).unwrap(),
+        spinlock.value_at(0)
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::VecDeque;
+
+    fn naive_spinlock(steps: usize, iterations: usize) -> VecDeque<usize> {
+        let mut spinlock = VecDeque::with_capacity(iterations);
+        spinlock.push_back(0);
+        Spinlock::new(steps)
+            .take(iterations.max(1) - 1)
+            .for_each(|insertion| spinlock.insert(insertion.position, insertion.value));
+        spinlock
+    }
+
+    #[test]
+    fn find_value_test() {
+        assert_eq!(find_value(3, 2018, 2017), 638);
+    }
+
+    #[test]
+    fn spinlock_test() {
+        let insertions = Spinlock::new(3).take(3).collect::<Vec<_>>();
+        assert_eq!(
+            insertions,
+            vec![
+                Insertion {
+                    step: 1,
+                    position: 1,
+                    value: 1,
+                },
+                Insertion {
+                    step: 2,
+                    position: 1,
+                    value: 2,
+                },
+                Insertion {
+                    step: 3,
+                    position: 2,
+                    value: 3,
+                },
+            ]
+        );
+
+        let mut spinlock = Spinlock::new(3);
+        spinlock.by_ref().take(3).for_each(drop);
+        assert_eq!(spinlock.undo(), insertions.last().cloned());
+        assert_eq!(spinlock.next(), insertions.last().cloned());
+        (0..3).for_each(|_| assert!(spinlock.undo().is_some()));
+        assert_eq!(spinlock.undo(), None);
+    }
+
+    #[test]
+    fn spinlock_ring_test() {
+        for &steps in &[1, 3, 7, 386] {
+            for &iterations in &[1, 2, 3, 10, 1000] {
+                let expected = naive_spinlock(steps, iterations);
+                let spinlock = SpinlockRing::new(steps, iterations);
+                for (pos, &value) in expected.iter().enumerate() {
+                    assert_eq!(spinlock.value_at(pos), value);
+                    assert_eq!(spinlock.position_of(value), Some(pos));
+                    assert_eq!(
+                        spinlock.value_after(value),
+                        Some(expected[(pos + 1) % expected.len()])
+                    );
+                }
+                assert_eq!(spinlock.value_after(iterations), None);
+            }
+        }
+    }
+
+    #[test]
+    fn render_test() {
+        let expected = [
+            "(0)",
+            "0 (1)",
+            "0 (2) 1",
+            "0  2 (3) 1",
+            "0  2 (4) 3  1",
+            "0 (5) 2  4  3  1",
+            "0  5  2  4  3 (6) 1",
+            "0  5 (7) 2  4  3  6  1",
+            "0  5  7  2  4  3 (8) 6  1",
+            "0 (9) 5  7  2  4  3  8  6  1",
+        ];
+        let mut spinlock = Spinlock::new(3);
+        for line in expected.iter() {
+            assert_eq!(spinlock.ring().render(10), *line);
+            spinlock.next();
+        }
+        assert_eq!(
+            SpinlockRing::new(3, 2018).render(3),
+            "1512  1134  151 (2017) 638  1513  851"
+        );
+    }
 
     #[test]
-    fn create_spin_lock_test() {
-        assert_eq!(create_spin_lock(3, 2017), 638);
+    fn find_zero_test() {
+        let spinlock = SpinlockRing::new(3, 100_000);
+        assert_eq!(spinlock.value_after(0), Some(find_zero(3, 100_000)));
     }
 }
@@ -0,0 +1,175 @@
+use std::io::Write;
+
+use failure::Error;
+
+use super::{Collision, Coordinates, Particle};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    XY,
+    XZ,
+    YZ,
+}
+
+impl Projection {
+    fn project(self, coordinates: Coordinates) -> (i64, i64) {
+        match self {
+            Projection::XY => (coordinates.x, coordinates.y),
+            Projection::XZ => (coordinates.x, coordinates.z),
+            Projection::YZ => (coordinates.y, coordinates.z),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Projection::XY => "xy",
+            Projection::XZ => "xz",
+            Projection::YZ => "yz",
+        }
+    }
+}
+
+// Every tick from 0 to `ticks` along with the positions of the particles
+// still around, by index into `particles`. Particles are last listed on the
+// tick they collide.
+fn swarm_ticks<'a>(
+    particles: &'a [Particle],
+    collisions: &'a [Collision],
+    ticks: i64,
+) -> impl Iterator<Item = (i64, Vec<(usize, Coordinates)>)> + 'a {
+    let destroyed_at = destroyed_at(particles.len(), collisions);
+    (0..ticks + 1).map(move |tick| {
+        let positions = particles
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| destroyed_at[i].is_none_or(|destroyed| tick <= destroyed))
+            .map(|(i, particle)| (i, particle.position_at(tick)))
+            .collect();
+        (tick, positions)
+    })
+}
+
+fn destroyed_at(len: usize, collisions: &[Collision]) -> Vec<Option<i64>> {
+    let mut destroyed_at = vec![None; len];
+    for collision in collisions {
+        for &i in &collision.particles {
+            destroyed_at[i] = Some(collision.tick);
+        }
+    }
+    destroyed_at
+}
+
+pub fn write_csv<W: Write>(
+    writer: &mut W,
+    particles: &[Particle],
+    collisions: &[Collision],
+    ticks: i64,
+) -> Result<(), Error> {
+    writeln!(writer, "tick,particle,x,y,z")?;
+    for (tick, positions) in swarm_ticks(particles, collisions, ticks) {
+        for (i, position) in positions {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                tick, i, position.x, position.y, position.z
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_json_lines<W: Write>(
+    writer: &mut W,
+    particles: &[Particle],
+    collisions: &[Collision],
+    ticks: i64,
+) -> Result<(), Error> {
+    for (tick, positions) in swarm_ticks(particles, collisions, ticks) {
+        let positions = positions
+            .into_iter()
+            .map(|(i, position)| {
+                format!(
+                    "{{\"particle\":{},\"x\":{},\"y\":{},\"z\":{}}}",
+                    i, position.x, position.y, position.z
+                )
+            })
+            .collect::<Vec<_>>();
+        writeln!(
+            writer,
+            "{{\"tick\":{},\"particles\":[{}]}}",
+            tick,
+            positions.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+// Plots each particle's trajectory up to `ticks`, or until it is destroyed in
+// one of `collisions`, with a red marker wherever particles collided.
+pub fn write_svg<W: Write>(
+    writer: &mut W,
+    particles: &[Particle],
+    collisions: &[Collision],
+    ticks: i64,
+    projection: Projection,
+) -> Result<(), Error> {
+    let destroyed_at = destroyed_at(particles.len(), collisions);
+    let trajectories = particles
+        .iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let last = destroyed_at[i].map_or(ticks, |destroyed| destroyed.min(ticks));
+            (0..last + 1)
+                .map(|tick| projection.project(particle.position_at(tick)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let markers = collisions
+        .iter()
+        .filter(|collision| collision.tick <= ticks)
+        .map(|collision| projection.project(collision.position))
+        .collect::<Vec<_>>();
+
+    let points = trajectories
+        .iter()
+        .flat_map(|t| t.iter())
+        .chain(markers.iter());
+    let (min_x, min_y, max_x, max_y) = points.fold(
+        (i64::MAX, i64::MAX, i64::MIN, i64::MIN),
+        |(min_x, min_y, max_x, max_y), &(x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        },
+    );
+    let (width, height) = ((max_x - min_x).max(1), (max_y - min_y).max(1));
+    let radius = width.max(height) as f64 / 200.0;
+
+    writeln!(
+        writer,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+        min_x, min_y, width, height
+    )?;
+    writeln!(writer, "<title>Projection {}</title>", projection.name())?;
+    for (i, trajectory) in trajectories.iter().enumerate() {
+        let points = trajectory
+            .iter()
+            .map(|&(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<_>>();
+        writeln!(
+            writer,
+            "<polyline id=\"particle-{}\" points=\"{}\" fill=\"none\" stroke=\"black\" \
+             stroke-width=\"{}\"/>",
+            i,
+            points.join(" "),
+            radius / 4.0
+        )?;
+    }
+    for &(x, y) in &markers {
+        writeln!(
+            writer,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>",
+            x, y, radius
+        )?;
+    }
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
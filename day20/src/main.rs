@@ -3,12 +3,22 @@ extern crate failure;
 #[macro_use]
 extern crate nom;
 
+mod export;
+
 use failure::Error;
-use std::str::FromStr;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::mem;
 use std::ops::AddAssign;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 struct Coordinates {
     x: i64,
     y: i64,
@@ -21,6 +31,12 @@ impl Coordinates {
     }
 }
 
+impl fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{},{},{}>", self.x, self.y, self.z)
+    }
+}
+
 impl Ord for Coordinates {
     fn cmp(&self, other: &Coordinates) -> Ordering {
         self.manhattan_distance().cmp(&other.manhattan_distance())
@@ -41,7 +57,7 @@ impl AddAssign for Coordinates {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 struct Particle {
     position: Coordinates,
     velocity: Coordinates,
@@ -53,27 +69,46 @@ impl Particle {
         self.velocity += self.acceleration;
         self.position += self.velocity;
     }
-}
 
-impl Ord for Particle {
-    fn cmp(&self, other: &Particle) -> Ordering {
-        let ord = self.acceleration.cmp(&other.acceleration);
-        match ord {
-            Ordering::Less | Ordering::Greater => return ord,
-            _ => (),
-        }
-        let ord = self.velocity.cmp(&other.velocity);
-        match ord {
-            Ordering::Less | Ordering::Greater => return ord,
-            _ => (),
-        }
-        self.position.cmp(&other.position)
+    fn advance(&mut self, ticks: i64) {
+        self.position = self.position_at(ticks);
+        let axis = |v: i64, a: i64| v + a * ticks;
+        self.velocity = Coordinates {
+            x: axis(self.velocity.x, self.acceleration.x),
+            y: axis(self.velocity.y, self.acceleration.y),
+            z: axis(self.velocity.z, self.acceleration.z),
+        };
+    }
+
+    fn position_at(&self, tick: i64) -> Coordinates {
+        self.checked_position_at(tick)
+            .expect("position out of range")
+    }
+
+    // The position after `tick` ticks, worked out in i128 so that ticks found
+    // by solving for collisions can be checked, or None if it doesn't fit.
+    fn checked_position_at(&self, tick: i64) -> Option<Coordinates> {
+        let tick = i128::from(tick);
+        let axis = |p: i64, v: i64, a: i64| {
+            let accelerated = i128::from(a).checked_mul(tick * (tick + 1) / 2)?;
+            let position = (i128::from(p) + i128::from(v) * tick).checked_add(accelerated)?;
+            i64::try_from(position).ok()
+        };
+        Some(Coordinates {
+            x: axis(self.position.x, self.velocity.x, self.acceleration.x)?,
+            y: axis(self.position.y, self.velocity.y, self.acceleration.y)?,
+            z: axis(self.position.z, self.velocity.z, self.acceleration.z)?,
+        })
     }
 }
 
-impl PartialOrd for Particle {
-    fn partial_cmp(&self, other: &Particle) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for Particle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "p={}, v={}, a={}",
+            self.position, self.velocity, self.acceleration
+        )
     }
 }
 
@@ -92,7 +127,7 @@ impl FromStr for Particle {
                do_parse!(tag!("<") >> x: integer >> tag!(",")
                                    >> y: integer >> tag!(",")
                                    >> z: integer >> tag!(">")
-                                   >> (Coordinates { x: x, y: y, z: z })));
+                                   >> (Coordinates { x, y, z })));
 
         do_parse!(
             s,
@@ -103,8 +138,9 @@ impl FromStr for Particle {
                 velocity: v,
                 acceleration: a,
             })
-        ).to_result()
-            .map_err(|e| format_err!("{}", e))
+        )
+        .to_result()
+        .map_err(|e| format_err!("{}", e))
     }
 }
 
@@ -112,59 +148,755 @@ fn parse_input(input: &str) -> Result<Vec<Particle>, Error> {
     input.split('\n').map(str::parse::<Particle>).collect()
 }
 
-fn closest_to_root(particles: &[Particle]) -> Option<usize> {
-    let min = particles.iter().min()?;
-    particles.iter().position(|p| p == min)
+// Once position, velocity and acceleration agree in sign on an axis, the
+// distance along it only ever grows and is a polynomial in the tick.
+fn settled_axis(p: i64, v: i64, a: i64) -> Option<i64> {
+    let sign = [a, v, p]
+        .iter()
+        .map(|n| n.signum())
+        .find(|&sign| sign != 0)
+        .unwrap_or(0);
+    if p * sign >= 0 && v * sign >= 0 && a * sign >= 0 {
+        Some(sign)
+    } else {
+        None
+    }
 }
 
-fn remove_collisions(particles: &mut Vec<Particle>) {
-    fn position_sort(a: &Particle, b: &Particle) -> Ordering {
-        let a = a.position;
-        let b = b.position;
-        let ord = a.x.cmp(&b.x);
-        match ord {
-            Ordering::Less | Ordering::Greater => return ord,
-            _ => (),
+// Distance from the origin as `constant + linear * t + quadratic * t * (t + 1) / 2`
+// for every tick `t` from `settled` onwards.
+#[derive(Debug, PartialEq)]
+struct LongTermDistance {
+    settled: i64,
+    quadratic: i64,
+    linear: i64,
+    constant: i64,
+}
+
+impl LongTermDistance {
+    fn new(particle: &Particle) -> LongTermDistance {
+        let mut current = particle.clone();
+        let mut settled = 0;
+        loop {
+            let signs = (
+                settled_axis(
+                    current.position.x,
+                    current.velocity.x,
+                    current.acceleration.x,
+                ),
+                settled_axis(
+                    current.position.y,
+                    current.velocity.y,
+                    current.acceleration.y,
+                ),
+                settled_axis(
+                    current.position.z,
+                    current.velocity.z,
+                    current.acceleration.z,
+                ),
+            );
+            if let (Some(x), Some(y), Some(z)) = signs {
+                let sum = |c: Coordinates| x * c.x + y * c.y + z * c.z;
+                return LongTermDistance {
+                    settled,
+                    quadratic: sum(particle.acceleration),
+                    linear: sum(particle.velocity),
+                    constant: sum(particle.position),
+                };
+            }
+            current.tick();
+            settled += 1;
+        }
+    }
+
+    fn key(&self) -> (i64, i64, i64) {
+        (self.quadratic, self.linear, self.constant)
+    }
+
+    // The first tick from which `self` is never further away than `other`,
+    // given that it is in the long run.
+    fn never_further_from(&self, other: &LongTermDistance) -> i64 {
+        let da = other.quadratic - self.quadratic;
+        let dv = other.linear - self.linear;
+        let dp = other.constant - self.constant;
+        let difference = |t: i64| dp + dv * t + da * t * (t + 1) / 2;
+
+        let start = self.settled.max(other.settled);
+        let (a, b, c) = (da as f64, 2.0 * dv as f64 + da as f64, 2.0 * dp as f64);
+        let root = if a != 0.0 {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                None
+            } else {
+                Some((-b + discriminant.sqrt()) / (2.0 * a))
+            }
+        } else if b != 0.0 {
+            Some(-c / b)
+        } else {
+            None
+        };
+
+        let mut tick = root.map_or(start, |root| start.max(root.ceil() as i64 + 1));
+        while difference(tick) < 0 {
+            tick += 1;
         }
-        let ord = a.y.cmp(&b.y);
-        match ord {
-            Ordering::Less | Ordering::Greater => return ord,
-            _ => (),
+        while tick > start && difference(tick - 1) >= 0 {
+            tick -= 1;
         }
-        a.z.cmp(&b.z)
+        tick
     }
-    particles.sort_unstable_by(position_sort);
+}
+
+#[derive(Debug, PartialEq)]
+struct ClosestParticle {
+    index: usize,
+    certificate: i64,
+}
+
+// The particle that stays closest to the origin in the long run, along with a
+// tick after which no other particle is ever strictly closer.
+fn long_term_closest(particles: &[Particle]) -> Option<ClosestParticle> {
+    let distances = particles
+        .iter()
+        .map(LongTermDistance::new)
+        .collect::<Vec<_>>();
+    let (index, closest) = distances
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, distance)| distance.key())?;
+    let mut certificate = distances
+        .iter()
+        .map(|distance| closest.never_further_from(distance))
+        .max()?;
 
-    let mut i = 0;
-    while i != particles.len() {
-        let pos = particles[i].position;
-        let count = particles
+    // The distances are only polynomials once settled, so check the earlier
+    // ticks directly.
+    let distance = |particle: &Particle, tick| particle.position_at(tick).manhattan_distance();
+    while certificate > 0 {
+        let best = distance(&particles[index], certificate - 1);
+        if particles
             .iter()
-            .skip(i)
-            .take_while(|particle| particle.position == pos)
-            .count();
-        if count > 1 {
-            particles.drain(i..(i + count));
+            .any(|particle| distance(particle, certificate - 1) < best)
+        {
+            break;
+        }
+        certificate -= 1;
+    }
+    Some(ClosestParticle { index, certificate })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Collision {
+    tick: i64,
+    position: Coordinates,
+    particles: Vec<usize>,
+}
+
+// The square root of `n` rounded down, by Newton's method from a power of two
+// no smaller than it.
+fn isqrt(n: i128) -> i128 {
+    if n < 2 {
+        return n;
+    }
+    let mut root = 1 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (root + n / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+fn exact_sqrt(n: i128) -> Option<i128> {
+    if n < 0 {
+        return None;
+    }
+    let root = isqrt(n);
+    if root * root == n {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+// Positive ticks at which two particles with the given differences in
+// position, velocity and acceleration meet along one axis, or None if they
+// always do. After t ticks the difference is dp + dv * t + da * t * (t + 1) / 2.
+fn axis_collision_ticks(dp: i64, dv: i64, da: i64) -> Option<Vec<i64>> {
+    let (a, b, c) = (da as i128, 2 * dv as i128 + da as i128, 2 * dp as i128);
+    let roots = if a == 0 && b == 0 {
+        if c == 0 {
+            return None;
+        }
+        vec![]
+    } else if a == 0 {
+        if c % b == 0 {
+            vec![-c / b]
         } else {
-            i += 1;
+            vec![]
         }
+    } else {
+        exact_sqrt(b * b - 4 * a * c)
+            .map(|root| {
+                [-b - root, -b + root]
+                    .iter()
+                    .filter(|&&n| n % (2 * a) == 0)
+                    .map(|&n| n / (2 * a))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Some(
+        roots
+            .into_iter()
+            .filter(|&t| t >= 1)
+            .map(|t| t as i64)
+            .collect(),
+    )
+}
+
+fn collision_tick(a: &Particle, b: &Particle) -> Option<i64> {
+    let axes = [
+        (
+            a.position.x - b.position.x,
+            a.velocity.x - b.velocity.x,
+            a.acceleration.x - b.acceleration.x,
+        ),
+        (
+            a.position.y - b.position.y,
+            a.velocity.y - b.velocity.y,
+            a.acceleration.y - b.acceleration.y,
+        ),
+        (
+            a.position.z - b.position.z,
+            a.velocity.z - b.velocity.z,
+            a.acceleration.z - b.acceleration.z,
+        ),
+    ];
+    match axes
+        .iter()
+        .filter_map(|&(dp, dv, da)| axis_collision_ticks(dp, dv, da))
+        .next()
+    {
+        Some(ticks) => ticks
+            .into_iter()
+            .filter(|&tick| {
+                let position = a.checked_position_at(tick);
+                position.is_some() && position == b.checked_position_at(tick)
+            })
+            .min(),
+        None => Some(1),
+    }
+}
+
+// Solves for the first tick each pair of particles meets and replays those
+// meetings in order, skipping any involving a particle that already collided.
+fn find_collisions(particles: &[Particle]) -> Vec<Collision> {
+    let mut events = Vec::new();
+    for i in 0..particles.len() {
+        for j in i + 1..particles.len() {
+            if let Some(tick) = collision_tick(&particles[i], &particles[j]) {
+                events.push((tick, i, j));
+            }
+        }
+    }
+    events.sort_unstable();
+
+    let mut destroyed = vec![false; particles.len()];
+    let mut collisions = Vec::new();
+    let mut start = 0;
+    while start != events.len() {
+        let tick = events[start].0;
+        let end = start
+            + events[start..]
+                .iter()
+                .take_while(|&&(t, _, _)| t == tick)
+                .count();
+        let mut tick_collisions: Vec<Collision> = Vec::new();
+        for &(_, i, j) in &events[start..end] {
+            if destroyed[i] || destroyed[j] {
+                continue;
+            }
+            let position = particles[i].position_at(tick);
+            match tick_collisions.iter_mut().find(|c| c.position == position) {
+                Some(collision) => collision.particles.extend_from_slice(&[i, j]),
+                None => tick_collisions.push(Collision {
+                    tick,
+                    position,
+                    particles: vec![i, j],
+                }),
+            }
+        }
+        for collision in &mut tick_collisions {
+            collision.particles.sort_unstable();
+            collision.particles.dedup();
+            collision
+                .particles
+                .iter()
+                .for_each(|&i| destroyed[i] = true);
+        }
+        collisions.extend(tick_collisions);
+        start = end;
+    }
+    collisions
+}
+
+fn remove_collisions(particles: &mut Vec<Particle>) {
+    let mut counts = HashMap::with_capacity(particles.len());
+    for particle in particles.iter() {
+        *counts.entry(particle.position).or_insert(0) += 1;
     }
+    particles.retain(|particle| counts[&particle.position] == 1);
 }
 
-fn collide_particles(mut particles: &mut Vec<Particle>, iters: usize) {
-    (0..iters).for_each(|_| {
+// Removes and returns the particles that can never meet another one again.
+// Along a single axis a particle that is strictly ahead of everything behind
+// it, and no slower or less accelerated than any of them, can never be caught,
+// and likewise for everything ahead of it.
+fn retire_escaped(particles: &mut Vec<Particle>) -> Vec<Particle> {
+    let axes: [fn(&Coordinates) -> i64; 3] = [|c| c.x, |c| c.y, |c| c.z];
+    let mut escaped = vec![false; particles.len()];
+    let mut order = (0..particles.len()).collect::<Vec<_>>();
+    for axis in axes.iter() {
+        let key = |i: usize| {
+            let particle = &particles[i];
+            (
+                axis(&particle.position),
+                axis(&particle.velocity),
+                axis(&particle.acceleration),
+            )
+        };
+        order.sort_unstable_by_key(|&i| axis(&particles[i].position));
+
+        // Maximum velocity and acceleration of everything strictly behind each
+        // particle, and the minimum of everything strictly ahead.
+        let mut behind = vec![(i64::MIN, i64::MIN); order.len()];
+        let mut start = 0;
+        let mut max = (i64::MIN, i64::MIN);
+        while start != order.len() {
+            let (p, _, _) = key(order[start]);
+            let end = start
+                + order[start..]
+                    .iter()
+                    .take_while(|&&i| key(i).0 == p)
+                    .count();
+            for &i in &order[start..end] {
+                behind[i] = max;
+            }
+            for &i in &order[start..end] {
+                let (_, v, a) = key(i);
+                max = (max.0.max(v), max.1.max(a));
+            }
+            start = end;
+        }
+        let mut end = order.len();
+        let mut min = (i64::MAX, i64::MAX);
+        while end != 0 {
+            let (p, _, _) = key(order[end - 1]);
+            let start = end
+                - order[..end]
+                    .iter()
+                    .rev()
+                    .take_while(|&&i| key(i).0 == p)
+                    .count();
+            for &i in &order[start..end] {
+                let (_, v, a) = key(i);
+                let (behind_v, behind_a) = behind[i];
+                if end - start == 1 && behind_v <= v && behind_a <= a && v <= min.0 && a <= min.1 {
+                    escaped[i] = true;
+                }
+            }
+            for &i in &order[start..end] {
+                let (_, v, a) = key(i);
+                min = (min.0.min(v), min.1.min(a));
+            }
+            end = start;
+        }
+    }
+
+    let mut retired = Vec::new();
+    let mut escaped = escaped.into_iter();
+    for particle in mem::take(particles) {
+        if escaped.next().unwrap() {
+            retired.push(particle);
+        } else {
+            particles.push(particle);
+        }
+    }
+    retired
+}
+
+fn collide_particles(particles: &mut Vec<Particle>, iters: usize) {
+    const RETIRE_INTERVAL: usize = 16;
+
+    let mut retired = Vec::new();
+    for tick in 0..iters {
+        if particles.is_empty() {
+            break;
+        }
+        if tick % RETIRE_INTERVAL == 0 {
+            retired.extend(
+                retire_escaped(particles)
+                    .into_iter()
+                    .map(|particle| (tick, particle)),
+            );
+        }
         particles.iter_mut().for_each(Particle::tick);
-        remove_collisions(&mut particles);
-    });
+        remove_collisions(particles);
+    }
+    particles.extend(retired.into_iter().map(|(tick, mut particle)| {
+        particle.advance((iters - tick) as i64);
+        particle
+    }));
 }
 
 fn main() {
     let input = include_str!("input.txt");
     let input = input.trim();
-    let mut particles = parse_input(&input).expect("parse");
+    let mut particles = parse_input(input).expect("parse");
+
+    let closest = long_term_closest(&particles).unwrap();
+    println!(
+        "Result 1: {} (closest from tick {})",
+        closest.index, closest.certificate
+    );
+
+    let collisions = find_collisions(&particles);
+    let destroyed = collisions
+        .iter()
+        .map(|collision| collision.particles.len())
+        .sum::<usize>();
+    println!("Result 2: {}", particles.len() - destroyed);
+    for collision in &collisions {
+        println!(
+            "Tick {}: particles {:?} collided at {:?}",
+            collision.tick, collision.particles, collision.position
+        );
+    }
+
+    let last_tick = collisions.last().map_or(0, |collision| collision.tick);
+    if let Some(dir) = env::args().nth(1) {
+        export_swarm(Path::new(&dir), &particles, &collisions, last_tick + 10).expect("export");
+    }
+
+    collide_particles(&mut particles, last_tick as usize);
+    println!("Simulated to tick {}: {}", last_tick, particles.len());
+}
+
+fn export_swarm(
+    dir: &Path,
+    particles: &[Particle],
+    collisions: &[Collision],
+    ticks: i64,
+) -> Result<(), Error> {
+    let create = |name: &str| -> Result<BufWriter<File>, Error> {
+        Ok(BufWriter::new(File::create(dir.join(name))?))
+    };
+    export::write_csv(&mut create("particles.csv")?, particles, collisions, ticks)?;
+    export::write_json_lines(
+        &mut create("particles.jsonl")?,
+        particles,
+        collisions,
+        ticks,
+    )?;
+    for &projection in &[
+        export::Projection::XY,
+        export::Projection::XZ,
+        export::Projection::YZ,
+    ] {
+        let name = format!("trajectories-{}.svg", projection.name());
+        export::write_svg(
+            &mut create(&name)?,
+            particles,
+            collisions,
+            ticks,
+            projection,
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn display_particle_test() {
+    let input = "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>
+p=<1,-2,3>, v=<-1,0,7>, a=<0,-1,0>";
+    let particles = parse_input(input).unwrap();
+    let output = particles
+        .iter()
+        .map(|particle| particle.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(output, input);
+}
+
+#[test]
+fn export_test() {
+    let particles = parse_input(
+        "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>
+p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>
+p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>",
+    )
+    .unwrap();
+    let collisions = find_collisions(&particles);
+
+    let mut csv = Vec::new();
+    export::write_csv(&mut csv, &particles, &collisions, 3).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "tick,particle,x,y,z");
+    assert_eq!(lines[1], "0,0,-6,0,0");
+    assert_eq!(lines[9], "2,0,0,0,0");
+    assert_eq!(lines[13], "3,3,0,0,0");
+    assert_eq!(lines.len(), 14);
+
+    let mut json = Vec::new();
+    export::write_json_lines(&mut json, &particles, &collisions, 3).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert_eq!(json.lines().count(), 4);
+    assert_eq!(
+        json.lines().last().unwrap(),
+        "{\"tick\":3,\"particles\":[{\"particle\":3,\"x\":0,\"y\":0,\"z\":0}]}"
+    );
+
+    let mut svg = Vec::new();
+    export::write_svg(&mut svg, &particles, &collisions, 3, export::Projection::XY).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert_eq!(svg.matches("<polyline").count(), 4);
+    assert_eq!(svg.matches("<circle").count(), 1);
+    assert!(svg.contains("<circle cx=\"0\" cy=\"0\""));
+}
+
+#[test]
+fn long_term_closest_test() {
+    let particles = parse_input(
+        "p=<3,0,0>, v=<2,0,0>, a=<-1,0,0>
+p=<4,0,0>, v=<0,0,0>, a=<-2,0,0>",
+    )
+    .unwrap();
+    assert_eq!(
+        long_term_closest(&particles),
+        Some(ClosestParticle {
+            index: 0,
+            certificate: 3,
+        })
+    );
 
-    println!("Result 1: {}", closest_to_root(&particles).unwrap());
+    // The old ordering preferred the particle with the slower velocity.
+    let particles = parse_input(
+        "p=<0,0,0>, v=<0,0,0>, a=<1,0,0>
+p=<0,0,0>, v=<-10,0,0>, a=<1,0,0>",
+    )
+    .unwrap();
+    let closest = long_term_closest(&particles).unwrap();
+    assert_eq!(closest.index, 1);
 
+    let distance = |particle: &Particle, tick| particle.position_at(tick).manhattan_distance();
+    for tick in closest.certificate..closest.certificate + 1_000 {
+        assert!(distance(&particles[1], tick) <= distance(&particles[0], tick));
+    }
+    assert!(
+        distance(&particles[1], closest.certificate - 1)
+            > distance(&particles[0], closest.certificate - 1)
+    );
+}
+
+#[test]
+fn long_term_closest_input_test() {
+    let input = include_str!("input.txt");
+    let particles = parse_input(input.trim()).unwrap();
+    let closest = long_term_closest(&particles).unwrap();
+    let distance = |particle: &Particle, tick| particle.position_at(tick).manhattan_distance();
+    for tick in closest.certificate..closest.certificate + 1_000 {
+        let best = distance(&particles[closest.index], tick);
+        assert!(particles
+            .iter()
+            .all(|particle| distance(particle, tick) >= best));
+    }
+}
+
+// Repeatable random numbers for tests, from a linear congruential generator.
+#[cfg(test)]
+struct Random(u64);
+
+#[cfg(test)]
+impl Random {
+    // A number from `-range` to `range`.
+    fn within(&mut self, range: i64) -> i64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % (2 * range as u64 + 1)) as i64 - range
+    }
+}
+
+// A swarm of particles crowded around the origin so that plenty collide.
+#[cfg(test)]
+fn generate_swarm(count: usize, seed: u64) -> Vec<Particle> {
+    let mut random = Random(seed);
+    let mut next = |range| random.within(range);
+    (0..count)
+        .map(|_| Particle {
+            position: Coordinates {
+                x: next(20),
+                y: next(20),
+                z: next(20),
+            },
+            velocity: Coordinates {
+                x: next(3),
+                y: next(3),
+                z: next(3),
+            },
+            acceleration: Coordinates {
+                x: next(1),
+                y: next(1),
+                z: next(1),
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn collide_particles_swarm_test() {
+    for seed in 0..5 {
+        let particles = generate_swarm(2_000, seed);
+        let collisions = find_collisions(&particles);
+        let destroyed = collisions
+            .iter()
+            .map(|collision| collision.particles.len())
+            .sum::<usize>();
+        let last_tick = collisions.last().map_or(0, |collision| collision.tick);
+
+        let mut simulated = particles.clone();
+        collide_particles(&mut simulated, last_tick as usize + 10);
+        assert_eq!(simulated.len(), particles.len() - destroyed);
+
+        let mut expected = particles.clone();
+        expected
+            .iter_mut()
+            .for_each(|particle| particle.advance(last_tick + 10));
+        let positions = |particles: &[Particle]| {
+            let mut positions = particles
+                .iter()
+                .map(|particle| {
+                    (
+                        particle.position.x,
+                        particle.position.y,
+                        particle.position.z,
+                    )
+                })
+                .collect::<Vec<_>>();
+            positions.sort_unstable();
+            positions
+        };
+        let survivors = expected
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, _)| {
+                !collisions
+                    .iter()
+                    .any(|collision| collision.particles.contains(&i))
+            })
+            .map(|(_, particle)| particle)
+            .collect::<Vec<_>>();
+        assert_eq!(positions(&simulated), positions(&survivors));
+    }
+}
+
+#[test]
+fn retire_escaped_test() {
+    let mut particles = parse_input(
+        "p=<0,0,0>, v=<0,0,0>, a=<0,0,0>
+p=<5,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<-5,0,0>, v=<1,0,0>, a=<0,0,0>",
+    )
+    .unwrap();
+    let retired = retire_escaped(&mut particles);
+    assert_eq!(retired.len(), 1);
+    assert_eq!(retired[0].position.x, 5);
+    assert_eq!(particles.len(), 2);
+}
+
+#[test]
+fn find_collisions_test() {
+    let particles = parse_input(
+        "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>
+p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>
+p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>",
+    )
+    .unwrap();
+    assert_eq!(
+        find_collisions(&particles),
+        vec![Collision {
+            tick: 2,
+            position: Coordinates { x: 0, y: 0, z: 0 },
+            particles: vec![0, 1, 2],
+        },]
+    );
+}
+
+#[test]
+fn collision_tick_test() {
+    let particles = parse_input(
+        "p=<0,0,0>, v=<0,0,0>, a=<1,0,0>
+p=<3,0,0>, v=<0,0,0>, a=<0,0,0>
+p=<1,0,0>, v=<0,0,0>, a=<1,0,0>
+p=<0,0,0>, v=<0,0,0>, a=<1,0,0>",
+    )
+    .unwrap();
+    assert_eq!(collision_tick(&particles[0], &particles[1]), Some(2));
+    assert_eq!(collision_tick(&particles[0], &particles[2]), None);
+    assert_eq!(collision_tick(&particles[0], &particles[3]), Some(1));
+
+    // Meeting after two billion ticks, where only the final positions fit in
+    // an i64, and later still where they don't.
+    let particles = parse_input(
+        "p=<2000000000,0,0>, v=<-1,0,0>, a=<3,0,0>
+p=<0,0,0>, v=<0,0,0>, a=<3,0,0>
+p=<2000000000,0,0>, v=<-1,0,0>, a=<5,0,0>
+p=<0,0,0>, v=<0,0,0>, a=<5,0,0>",
+    )
+    .unwrap();
+    assert_eq!(
+        collision_tick(&particles[0], &particles[1]),
+        Some(2_000_000_000)
+    );
+    assert_eq!(
+        particles[1].position_at(2_000_000_000).x,
+        6_000_000_003_000_000_000
+    );
+    assert_eq!(collision_tick(&particles[2], &particles[3]), None);
+}
+
+#[test]
+fn axis_collision_ticks_extreme_test() {
+    assert_eq!(isqrt(i128::MAX), 13043817825332782212);
+    assert_eq!(exact_sqrt((1 << 126) - (1 << 64) + 1), Some((1 << 63) - 1));
+    assert_eq!(exact_sqrt((1 << 126) - 1), None);
+
+    // Far too large a discriminant for f64 to find its root within one.
+    let (dp, dv, da) = (-4611686018427387902, 4611686018427387900, 2);
+    assert_eq!(axis_collision_ticks(dp, dv, da), Some(vec![1]));
+    assert_eq!(axis_collision_ticks(i64::MIN, i64::MAX, 0), Some(vec![]));
+    assert_eq!(
+        axis_collision_ticks(i64::MIN, -(i64::MIN / 2), 0),
+        Some(vec![2])
+    );
+}
+
+#[test]
+fn find_collisions_simulated_test() {
+    let input = include_str!("input.txt");
+    let mut particles = parse_input(input.trim()).unwrap();
+    let collisions = find_collisions(&particles);
+    let destroyed = collisions
+        .iter()
+        .map(|collision| collision.particles.len())
+        .sum::<usize>();
+    let survivors = particles.len() - destroyed;
     collide_particles(&mut particles, 1_000);
-    println!("Result 2: {}", particles.len());
+    assert_eq!(particles.len(), survivors);
 }
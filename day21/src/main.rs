@@ -2,25 +2,85 @@
 extern crate failure;
 #[macro_use]
 extern crate nom;
+extern crate num_bigint;
 extern crate pathfinding;
 
+mod render;
+
 use failure::Error;
+use num_bigint::BigUint;
 use pathfinding::Matrix;
 use pathfinding::Weights;
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Pixel {
     On,
     Off,
 }
 
-struct Rule(Vec<Matrix<Pixel>>, Matrix<Pixel>);
+// A square block of pixels packed row by row into a bitmask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Pattern {
+    size: usize,
+    bits: u64,
+}
+
+impl Pattern {
+    fn from_matrix(matrix: &Matrix<Pixel>) -> Pattern {
+        Pattern {
+            size: matrix.rows(),
+            bits: matrix
+                .as_ref()
+                .iter()
+                .enumerate()
+                .filter(|&(_, &p)| p == Pixel::On)
+                .fold(0, |bits, (i, _)| bits | 1 << i),
+        }
+    }
+
+    fn all(size: usize) -> impl Iterator<Item = Pattern> {
+        (0..1u64 << (size * size)).map(move |bits| Pattern { size, bits })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = (0..self.size)
+            .map(|row| {
+                (0..self.size)
+                    .map(|col| {
+                        if self.bits & 1 << (row * self.size + col) != 0 {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("/"))
+    }
+}
+
+// Blocks are packed into a u64, so they can be at most 8x8.
+const MAX_RULE_SIZE: usize = 8;
+
+// Finding uncovered patterns tries all 2^(size * size) of them, which takes
+// seconds for 5x5 and days for 6x6.
+const MAX_UNCOVERED_SIZE: usize = 5;
+
+// A rule with the number of the input line it is on.
+struct Rule(usize, Vec<Matrix<Pixel>>, Matrix<Pixel>);
 
 impl Rule {
-    fn new(from: Matrix<Pixel>, to: Matrix<Pixel>) -> Rule {
+    fn new(line: usize, from: Matrix<Pixel>, to: Matrix<Pixel>) -> Rule {
         Rule(
+            line,
             vec![
                 from.rotated_cw(1),
                 from.rotated_cw(2),
@@ -34,124 +94,215 @@ impl Rule {
             to,
         )
     }
-    fn try_apply(&self, grid_slice: &Matrix<Pixel>) -> Option<Matrix<Pixel>> {
-        let &Rule(ref from, ref to) = self;
-        if from.iter().any(|g| g == grid_slice) {
-            Some(to.clone())
-        } else {
-            None
-        }
-    }
 }
 
+// Every rotation and flip of every rule's input is expanded up front, so
+// finding the rule for a block is a single lookup. Rules may map any block
+// size to any other, as long as each input size has a single output size.
 struct Rules {
-    two_by_two: Vec<Rule>,
-    three_by_three: Vec<Rule>,
+    rules: HashMap<Pattern, (usize, Matrix<Pixel>)>,
+    duplicates: Vec<(usize, usize)>,
+    sizes: BTreeMap<usize, usize>,
+    preferred: Vec<usize>,
 }
 
 impl Rules {
-    fn apply(&self, grid_slice: &Matrix<Pixel>) -> Result<Matrix<Pixel>, Error> {
-        match grid_slice.rows() {
-            2 => for rule in self.two_by_two.iter() {
-                if let Some(grid_slice) = rule.try_apply(&grid_slice) {
-                    return Ok(grid_slice);
-                }
-            },
-            3 => for rule in self.three_by_three.iter() {
-                if let Some(grid_slice) = rule.try_apply(&grid_slice) {
-                    return Ok(grid_slice);
+    fn from_rules(rules: Vec<Rule>) -> Result<Rules, Error> {
+        let mut lookup: HashMap<Pattern, (usize, Matrix<Pixel>)> = HashMap::new();
+        let mut duplicates = Vec::new();
+        let mut sizes = BTreeMap::new();
+        for Rule(line, from, to) in rules {
+            let size = from[0].rows();
+            if size > MAX_RULE_SIZE {
+                bail!(
+                    "Rule on line {} is larger than {}x{}.",
+                    line,
+                    MAX_RULE_SIZE,
+                    MAX_RULE_SIZE
+                );
+            }
+            if *sizes.entry(size).or_insert_with(|| to.rows()) != to.rows() {
+                bail!(
+                    "Rule on line {} turns a {}x{} block into a different size than earlier rules.",
+                    line,
+                    size,
+                    size
+                );
+            }
+            let mut seen = None;
+            for pattern in from.iter().map(Pattern::from_matrix) {
+                match lookup.get(&pattern) {
+                    Some(&(other, _)) if other == line => (),
+                    Some(&(other, ref other_to)) if *other_to == to => seen = Some(other),
+                    Some(&(other, _)) => bail!(
+                        "Rules on lines {} and {} conflict for {}.",
+                        other,
+                        line,
+                        pattern
+                    ),
+                    None => {
+                        lookup.insert(pattern, (line, to.clone()));
+                    }
                 }
-            },
-            _ => bail!("Invalid input."),
+            }
+            if let Some(other) = seen {
+                duplicates.push((other, line));
+            }
+        }
+        let preferred = sizes.keys().cloned().collect();
+        Ok(Rules {
+            rules: lookup,
+            duplicates,
+            sizes,
+            preferred,
+        })
+    }
+
+    // Sets the order in which block sizes are tried when a grid is divisible
+    // by several of them. Sizes left out are tried last, smallest first.
+    fn prefer(&mut self, sizes: &[usize]) -> Result<(), Error> {
+        if let Some(size) = sizes.iter().find(|size| !self.sizes.contains_key(size)) {
+            bail!("No rules for {}x{} blocks.", size, size);
         }
-        Err(format_err!("No rule matches."))
+        let mut preferred = sizes.to_vec();
+        preferred.extend(self.sizes.keys().filter(|size| !sizes.contains(size)));
+        self.preferred = preferred;
+        Ok(())
+    }
+
+    // The block size a grid of `rows` is split into, and what each block
+    // grows into.
+    fn block_sizes(&self, rows: usize) -> Result<(usize, usize), Error> {
+        self.preferred
+            .iter()
+            .find(|&&size| rows.is_multiple_of(size))
+            .map(|&size| (size, self.sizes[&size]))
+            .ok_or_else(|| format_err!("No rules for splitting a {}x{} grid.", rows, rows))
+    }
+
+    fn apply(&self, grid_slice: &Matrix<Pixel>) -> Result<Matrix<Pixel>, Error> {
+        let pattern = Pattern::from_matrix(grid_slice);
+        self.rules
+            .get(&pattern)
+            .map(|(_, to)| to.clone())
+            .ok_or_else(|| format_err!("No rule matches {}.", pattern))
+    }
+
+    // Line numbers of rules that repeat an earlier one.
+    fn duplicates(&self) -> &[(usize, usize)] {
+        &self.duplicates
+    }
+
+    fn uncovered<'a>(&'a self, size: usize) -> Result<impl Iterator<Item = Pattern> + 'a, Error> {
+        if size > MAX_UNCOVERED_SIZE {
+            bail!(
+                "Can't list patterns larger than {}x{}.",
+                MAX_UNCOVERED_SIZE,
+                MAX_UNCOVERED_SIZE
+            );
+        }
+        Ok(Pattern::all(size).filter(move |pattern| !self.rules.contains_key(pattern)))
+    }
+}
+
+named!(pixel<&str, Pixel>,
+       alt!(do_parse!(tag!("#") >> (Pixel::On)) |
+            do_parse!(tag!(".") >> (Pixel::Off))));
+
+named!(pixel_row<&str, Vec<Pixel>>, many1!(complete!(pixel)));
+
+named!(pixel_rows<&str, Vec<Vec<Pixel>>>,
+       separated_nonempty_list_complete!(tag!("/"), pixel_row));
+
+fn parse_matrix(s: &str) -> Result<Matrix<Pixel>, Error> {
+    let rows = match pixel_rows(s) {
+        nom::IResult::Done("", rows) => rows,
+        _ => bail!("Invalid pattern {}.", s),
+    };
+    if rows.iter().any(|row| row.len() != rows.len()) {
+        bail!("Pattern {} is not square.", s);
     }
+    Ok(Matrix::square_from_vec(rows.concat()))
+}
+
+// Numbers the lines of `s` from 1, leaving out blank ones.
+fn numbered_lines(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+fn parse_rules<'a, I: Iterator<Item = (usize, &'a str)>>(lines: I) -> Result<Rules, Error> {
+    let rules = lines
+        .map(|(number, line)| {
+            let mut parts = line.split("=>").map(str::trim);
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(from), Some(to), None) => {
+                    Ok(Rule::new(number, parse_matrix(from)?, parse_matrix(to)?))
+                }
+                _ => Err(format_err!("Invalid rule {} on line {}.", line, number)),
+            }
+        })
+        .collect::<Result<Vec<Rule>, Error>>()?;
+    Rules::from_rules(rules)
 }
 
 impl FromStr for Rules {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        named!(pixel<&str, Pixel>,
-               alt!(do_parse!(tag!("#") >> (Pixel::On)) |
-                    do_parse!(tag!(".") >> (Pixel::Off))));
-
-        named!(two <&str, [Pixel; 2]>,
-               do_parse!(p0: pixel >> p1: pixel >> ([p0, p1])));
-
-        named!(three<&str, [Pixel; 3]>,
-               do_parse!(p0: pixel >> p1: pixel >> p2: pixel >> ([p0, p1, p2])));
-
-        named!(four<&str, [Pixel; 4]>,
-               do_parse!(p0: pixel >> p1: pixel >> p2: pixel >> p3: pixel>> ([p0, p1, p2, p3])));
-
-        named!(matrix2<&str, Matrix<Pixel>>,
-               do_parse!(l0: two >> tag!("/") >> l1: two >>
-                         (Matrix::square_from_vec([l0, l1].concat()))));
-
-        named!(matrix3<&str, Matrix<Pixel>>,
-               do_parse!(l0: three >> tag!("/") >> l1: three >> tag!("/") >> l2: three >>
-                         (Matrix::square_from_vec([l0, l1, l2].concat()))));
-
-        named!(matrix4<&str, Matrix<Pixel>>,
-               do_parse!(l0: four >> tag!("/") >> l1: four >> tag!("/") >>
-                         l2: four >> tag!("/") >> l3: four >>
-                         (Matrix::square_from_vec([l0, l1, l2, l3].concat()))));
-
-        named!(rule<&str, Rule>,
-               alt!(do_parse!(from: matrix2 >> ws!(tag!("=>")) >>
-                              to: matrix3 >> (Rule::new(from, to))) |
-                    do_parse!(from: matrix3 >> ws!(tag!("=>")) >>
-                              to: matrix4 >> (Rule::new(from, to)))));
-
-        let (two_by_two, three_by_three) = s.split('\n')
-            .map(rule)
-            .map(nom::IResult::to_result)
-            .collect::<Result<Vec<Rule>, nom::ErrorKind>>()
-            .map_err(|e| format_err!("{}", e))?
-            .into_iter()
-            .partition(|&Rule(_, ref to)| if to.rows() == 3 { true } else { false });
-
-        Ok(Rules {
-            two_by_two: two_by_two,
-            three_by_three: three_by_three,
-        })
+        parse_rules(numbered_lines(s))
     }
 }
 
+const GLIDER: &str = ".#./..#/###";
+
+// The input is the rules, optionally preceded by a line holding the start
+// pattern. Without one the puzzle's glider is used.
+fn parse_input(input: &str) -> Result<(Matrix<Pixel>, Rules), Error> {
+    let mut lines = numbered_lines(input).peekable();
+    let start = match lines.peek() {
+        Some(&(_, line)) if !line.contains("=>") => {
+            lines.next();
+            line.trim()
+        }
+        _ => GLIDER,
+    };
+    Ok((parse_matrix(start)?, parse_rules(lines)?))
+}
+
 struct Grid<'a> {
     matrix: Matrix<Pixel>,
     rules: &'a Rules,
+    // Size of the blocks the last iteration's rules produced.
+    block_size: Option<usize>,
 }
 
 impl<'a> Grid<'a> {
-    fn new(rules: &'a Rules) -> Self {
-        use Pixel::*;
+    fn new(matrix: Matrix<Pixel>, rules: &'a Rules) -> Self {
         Grid {
-            matrix: Matrix::square_from_vec(vec![Off, On, Off, Off, Off, On, On, On, On]),
-            rules: rules,
+            matrix,
+            rules,
+            block_size: None,
         }
     }
 
     fn run_iteration(&mut self) -> Result<(), Error> {
         let rows = self.matrix.rows();
-        let (new, old) = if rows % 2 == 0 {
-            (3, 2)
-        } else if rows % 3 == 0 {
-            (4, 3)
-        } else {
-            bail!("Invalid state.");
-        };
+        let (old, new) = self.rules.block_sizes(rows)?;
 
-        let mut new_grid = Matrix::new_square(rows * new / old, Pixel::Off);
+        let mut new_grid = Matrix::new_square(rows / old * new, Pixel::Off);
         for i in 0..(rows / old) {
             for j in 0..(rows / old) {
-                let grid_slice = self.matrix
+                let grid_slice = self
+                    .matrix
                     .slice((i * old)..(i * old + old), (j * old)..(j * old + old));
                 new_grid.set_slice(&(i * new, j * new), &self.rules.apply(&grid_slice)?);
             }
         }
         self.matrix = new_grid;
+        self.block_size = Some(new);
         Ok(())
     }
 
@@ -163,8 +314,8 @@ impl<'a> Grid<'a> {
     }
 }
 
-fn on_after_iterations(iterations: usize, rules: &Rules) -> usize {
-    let mut grid = Grid::new(rules);
+fn on_after_iterations(start: &Matrix<Pixel>, iterations: usize, rules: &Rules) -> usize {
+    let mut grid = Grid::new(start.clone(), rules);
     (0..iterations)
         .map(|_| grid.run_iteration())
         .collect::<Result<Vec<_>, Error>>()
@@ -173,14 +324,266 @@ fn on_after_iterations(iterations: usize, rules: &Rules) -> usize {
     grid.count_on()
 }
 
+// Every three iterations a 3x3 block grows into a 9x9 one, which splits into
+// nine 3x3 blocks that evolve independently of each other. Counting per block
+// and iteration means the grid itself never has to be built.
+struct BlockCounter<'a> {
+    rules: &'a Rules,
+    children: HashMap<Matrix<Pixel>, Vec<Matrix<Pixel>>>,
+    counts: HashMap<(Matrix<Pixel>, usize), BigUint>,
+}
+
+impl<'a> BlockCounter<'a> {
+    fn new(rules: &'a Rules) -> Self {
+        BlockCounter {
+            rules,
+            children: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    fn children(&mut self, block: &Matrix<Pixel>) -> Result<Vec<Matrix<Pixel>>, Error> {
+        if let Some(children) = self.children.get(block) {
+            return Ok(children.clone());
+        }
+        let mut grid = Grid::new(block.clone(), self.rules);
+        (0..3).try_for_each(|_| grid.run_iteration())?;
+        let children = (0..3)
+            .flat_map(|i| (0..3).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                grid.matrix
+                    .slice((i * 3)..(i * 3 + 3), (j * 3)..(j * 3 + 3))
+            })
+            .collect::<Vec<_>>();
+        self.children.insert(block.clone(), children.clone());
+        Ok(children)
+    }
+
+    fn count_on(&mut self, block: &Matrix<Pixel>, iterations: usize) -> Result<BigUint, Error> {
+        if block.rows() != 3 {
+            bail!("Blocks must be 3x3.");
+        }
+        let key = (block.clone(), iterations);
+        if let Some(count) = self.counts.get(&key) {
+            return Ok(count.clone());
+        }
+        let count = if iterations < 3 {
+            let mut grid = Grid::new(block.clone(), self.rules);
+            (0..iterations).try_for_each(|_| grid.run_iteration())?;
+            BigUint::from(grid.count_on())
+        } else {
+            let mut count = BigUint::from(0u32);
+            for child in self.children(block)? {
+                count += self.count_on(&child, iterations - 3)?;
+            }
+            count
+        };
+        self.counts.insert(key, count.clone());
+        Ok(count)
+    }
+}
+
+// Only holds for the puzzle's 2->3 and 3->4 rules, where even grids are split
+// into 2x2 blocks.
+fn on_after_many_iterations(
+    start: &Matrix<Pixel>,
+    iterations: usize,
+    rules: &Rules,
+) -> Result<BigUint, Error> {
+    if [(3, 3), (4, 2), (6, 2), (9, 3)]
+        .iter()
+        .any(|&(rows, size)| rules.block_sizes(rows).ok() != Some((size, size + 1)))
+    {
+        bail!("Blocks can only be counted with 2x2 blocks growing into 3x3 ones.");
+    }
+    BlockCounter::new(rules).count_on(start, iterations)
+}
+
 fn main() {
     let input = include_str!("input.txt");
-    let input = input.trim();
-    let rules: Rules = input.parse().expect("parse");
+    let (start, mut rules) = parse_input(input).expect("parse");
+
+    // Arguments are preferred block sizes, and optionally `--frames DIR` to
+    // write the first five iterations out as images.
+    let mut args = env::args().skip(1);
+    let mut preferred = Vec::new();
+    let mut frames = None;
+    while let Some(arg) = args.next() {
+        if arg == "--frames" {
+            frames = args.next();
+        } else {
+            preferred.push(arg.parse::<usize>().expect("block size"));
+        }
+    }
+    rules.prefer(&preferred).expect("block sizes");
+
+    for &(first, second) in rules.duplicates() {
+        println!("Rule on line {} duplicates line {}", second, first);
+    }
+    for &size in rules.sizes.keys() {
+        match rules.uncovered(size).map(Iterator::count) {
+            Ok(0) => {}
+            Ok(uncovered) => println!("{} {}x{} patterns have no rule", uncovered, size, size),
+            Err(e) => println!("Skipping {}x{} coverage: {}", size, size, e),
+        }
+    }
 
-    let result = on_after_iterations(5, &rules);
+    let result = on_after_iterations(&start, 5, &rules);
     println!("Result 1: {}", result);
+    if let Some(dir) = frames {
+        render::write_frames(Path::new(&dir), &start, 5, &rules, 8, true).expect("frames");
+    }
 
-    let result = on_after_iterations(18, &rules);
+    let result = on_after_iterations(&start, 18, &rules);
     println!("Result 2: {}", result);
+
+    match on_after_many_iterations(&start, 300, &rules) {
+        Ok(result) => println!("After 300 iterations: {}", result),
+        Err(e) => println!("Skipping 300 iterations: {}", e),
+    }
+}
+
+#[test]
+fn pattern_test() {
+    let rules: Rules = ".#./..#/### => #..#/..../..../#..#".parse().unwrap();
+    let glider = Pattern::from_matrix(&parse_matrix(GLIDER).unwrap());
+    assert_eq!(glider.to_string(), GLIDER);
+    assert_eq!(rules.block_sizes(3).unwrap(), (3, 4));
+    assert!(rules.block_sizes(2).is_err());
+}
+
+#[test]
+fn rules_report_test() {
+    let rules: Rules = "../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#"
+        .parse()
+        .unwrap();
+    assert!(rules.duplicates().is_empty());
+    assert_eq!(rules.uncovered(2).unwrap().count(), 12);
+    assert_eq!(rules.uncovered(3).unwrap().count(), 504);
+    assert!(rules
+        .uncovered(2)
+        .unwrap()
+        .all(|p| p.bits.count_ones() != 1));
+
+    let rules: Rules = "../.# => ##./#../...\n#./.. => ##./#../..."
+        .parse()
+        .unwrap();
+    assert_eq!(rules.duplicates(), &[(1, 2)]);
+
+    let conflict = "../.# => ##./#../...\n#./.. => ##./#../..#".parse::<Rules>();
+    assert!(conflict.is_err());
+
+    // Line numbers count the start pattern and blank lines.
+    let input = "#./.#\n\n../.# => ##./#../...\n\n#./.. => ##./#../...\n";
+    assert_eq!(parse_input(input).unwrap().1.duplicates(), &[(3, 5)]);
+    let conflict = parse_input("#./.#\n../.# => ##./#../...\n\n#./.. => ##./#../..#");
+    assert_eq!(
+        conflict.err().unwrap().to_string(),
+        "Rules on lines 2 and 4 conflict for .#/..."
+    );
+
+    let input = include_str!("input.txt");
+    let rules: Rules = input.trim().parse().unwrap();
+    assert!(rules.duplicates().is_empty());
+    assert_eq!(rules.uncovered(2).unwrap().count(), 0);
+    assert_eq!(rules.uncovered(3).unwrap().count(), 0);
+    assert!(rules.uncovered(6).is_err());
+}
+
+#[test]
+fn on_after_many_iterations_test() {
+    let (start, rules) = parse_input(include_str!("input.txt")).unwrap();
+    for iterations in 0..12 {
+        assert_eq!(
+            on_after_many_iterations(&start, iterations, &rules).unwrap(),
+            BigUint::from(on_after_iterations(&start, iterations, &rules))
+        );
+    }
+}
+
+#[test]
+fn on_after_iterations_example_test() {
+    let (start, rules) =
+        parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#").unwrap();
+    assert_eq!(on_after_iterations(&start, 2, &rules), 12);
+    assert_eq!(
+        on_after_many_iterations(&start, 2, &rules).unwrap(),
+        BigUint::from(12u32)
+    );
+}
+
+#[test]
+fn render_test() {
+    let (start, rules) =
+        parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#").unwrap();
+    let mut grid = Grid::new(start, &rules);
+    assert_eq!(grid.to_string(), ".#.\n..#\n###");
+    assert_eq!(render::render_text(&grid, true), grid.to_string());
+    grid.run_iteration().unwrap();
+    grid.run_iteration().unwrap();
+    assert_eq!(
+        render::render_text(&grid, true),
+        "##.|##.\n\
+         #..|#..\n\
+         ...|...\n\
+         ---+---\n\
+         ##.|##.\n\
+         #..|#..\n\
+         ...|..."
+    );
+
+    let mut pgm = Vec::new();
+    render::write_pgm(&mut pgm, &grid, 1, true).unwrap();
+    let mut expected = b"P5\n7 7\n255\n".to_vec();
+    for line in render::render_text(&grid, true).lines() {
+        expected.extend(line.chars().map(|c| match c {
+            '#' => 0,
+            '.' => 255,
+            _ => 160,
+        }));
+    }
+    assert_eq!(pgm, expected);
+}
+
+#[test]
+fn general_rule_sizes_test() {
+    let input = "#.../..../..../....\n\
+                 #./.. => ##./.../...\n\
+                 ../.. => .../.../...\n\
+                 ##/.. => .../.../...\n\
+                 #.../..../..../.... => #####/#####/#####/#####/#####\n\
+                 #####/#####/#####/#####/##### => ######/######/######/######/######/######";
+    let (start, mut rules) = parse_input(input).unwrap();
+    assert_eq!(start.rows(), 4);
+    assert!(rules.prefer(&[3]).is_err());
+
+    // 4 -> 6 -> 9 with 2x2 blocks, and nothing splits 9.
+    assert_eq!(on_after_iterations(&start, 1, &rules), 2);
+    let mut grid = Grid::new(start.clone(), &rules);
+    grid.run_iteration().unwrap();
+    grid.run_iteration().unwrap();
+    assert_eq!(grid.matrix.rows(), 9);
+    assert!(grid.run_iteration().is_err());
+
+    // 4 -> 5 -> 6 when 4x4 blocks are preferred.
+    rules.prefer(&[4]).unwrap();
+    let mut grid = Grid::new(start.clone(), &rules);
+    grid.run_iteration().unwrap();
+    assert_eq!(grid.count_on(), 25);
+    grid.run_iteration().unwrap();
+    assert_eq!(grid.count_on(), 36);
+    assert!(on_after_many_iterations(&start, 3, &rules).is_err());
+}
+
+#[test]
+fn parse_errors_test() {
+    assert!("../.# => ##./#../...\n.#. => ##./#../..."
+        .parse::<Rules>()
+        .is_err());
+    assert!("../.# => ##./#../...\n#./.. => ####/#.../..../...."
+        .parse::<Rules>()
+        .is_err());
+    assert!("../.# => ##./#.x/...".parse::<Rules>().is_err());
+    assert!("../.#".parse::<Rules>().is_err());
+    assert!(parse_input(".#\n../.# => ##./#../...").is_err());
 }
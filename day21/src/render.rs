@@ -0,0 +1,132 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use failure::Error;
+use pathfinding::Matrix;
+use pathfinding::Weights;
+
+use super::{Grid, Pixel, Rules};
+
+impl fmt::Display for Pixel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pixel::On => write!(f, "#"),
+            Pixel::Off => write!(f, "."),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Grid<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", render_text(self, false))
+    }
+}
+
+// Where to draw a line between the blocks the last iteration produced.
+fn boundary(grid: &Grid, boundaries: bool, i: usize) -> bool {
+    boundaries && i > 0 && grid.block_size.is_some_and(|size| i.is_multiple_of(size))
+}
+
+// Renders the grid as in the puzzle, with `|` and `-` between the blocks the
+// rules produced when `boundaries` is set.
+pub fn render_text(grid: &Grid, boundaries: bool) -> String {
+    let size = grid.matrix.rows();
+    let mut lines = Vec::new();
+    for row in 0..size {
+        if boundary(grid, boundaries, row) {
+            lines.push(
+                (0..size)
+                    .map(|col| {
+                        if boundary(grid, boundaries, col) {
+                            "+-"
+                        } else {
+                            "-"
+                        }
+                    })
+                    .collect::<String>(),
+            );
+        }
+        lines.push(
+            (0..size)
+                .map(|col| {
+                    let pixel = grid.matrix[&(row, col)];
+                    if boundary(grid, boundaries, col) {
+                        format!("|{}", pixel)
+                    } else {
+                        pixel.to_string()
+                    }
+                })
+                .collect(),
+        );
+    }
+    lines.join("\n")
+}
+
+// Grey levels of on and off pixels, and of the lines between blocks.
+const ON: u8 = 0;
+const OFF: u8 = 255;
+const BOUNDARY: u8 = 160;
+
+// Writes a binary PGM with every pixel drawn as a `scale` sided square, on
+// ones black and off ones white. Block boundaries become one pixel wide grey
+// lines, so they stand out against either.
+pub fn write_pgm<W: Write>(
+    writer: &mut W,
+    grid: &Grid,
+    scale: usize,
+    boundaries: bool,
+) -> Result<(), Error> {
+    // Each image column or row maps to a grid one, or to a boundary line.
+    let axis = (0..grid.matrix.rows())
+        .flat_map(|i| {
+            let line = if boundary(grid, boundaries, i) {
+                Some(None)
+            } else {
+                None
+            };
+            line.into_iter().chain((0..scale).map(move |_| Some(i)))
+        })
+        .collect::<Vec<_>>();
+
+    writeln!(writer, "P5\n{} {}\n255", axis.len(), axis.len())?;
+    for &row in &axis {
+        let line = axis
+            .iter()
+            .map(|&col| match (row, col) {
+                (Some(row), Some(col)) if grid.matrix[&(row, col)] == Pixel::On => ON,
+                (Some(_), Some(_)) => OFF,
+                _ => BOUNDARY,
+            })
+            .collect::<Vec<_>>();
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
+// Writes one PGM per iteration, `frame-000.pgm` onwards, and all of them as
+// text to `frames.txt` separated by blank lines.
+pub fn write_frames(
+    dir: &Path,
+    start: &Matrix<Pixel>,
+    iterations: usize,
+    rules: &Rules,
+    scale: usize,
+    boundaries: bool,
+) -> Result<(), Error> {
+    let mut grid = Grid::new(start.clone(), rules);
+    let mut text = BufWriter::new(File::create(dir.join("frames.txt"))?);
+    for iteration in 0..iterations + 1 {
+        if iteration > 0 {
+            grid.run_iteration()?;
+            writeln!(text)?;
+        }
+        writeln!(text, "{}", render_text(&grid, boundaries))?;
+        let mut frame = BufWriter::new(File::create(
+            dir.join(format!("frame-{:03}.pgm", iteration)),
+        )?);
+        write_pgm(&mut frame, &grid, scale, boundaries)?;
+    }
+    Ok(())
+}
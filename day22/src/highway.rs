@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use super::{Carrier, Direction, World};
+
+// How many whole periods the carrier has to repeat itself for before the walk
+// is taken to be periodic, and how often to look for a period.
+const CONFIRM_PERIODS: usize = 3;
+const CHECK_INTERVAL: usize = 1024;
+// Half the width of the square around the carrier compared a period apart.
+const RADIUS: i64 = 8;
+
+#[derive(Clone, Copy)]
+struct Step {
+    position: (i64, i64),
+    direction: Direction,
+    node: usize,
+}
+
+// A walk that repeats the same `period` bursts from `start` onwards, moving
+// the carrier by `displacement` each time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highway {
+    pub start: usize,
+    pub period: usize,
+    pub displacement: (i64, i64),
+    pub infections_per_period: u64,
+    // Infections after each of the `period` bursts from `start` on.
+    infections: Vec<u64>,
+}
+
+impl Highway {
+    // Infections after `bursts` bursts, or `None` before the highway starts.
+    pub fn infections_after(&self, bursts: u64) -> Option<u64> {
+        let start = self.start as u64;
+        if bursts < start {
+            return None;
+        }
+        let (periods, rest) = (
+            (bursts - start) / self.period as u64,
+            (bursts - start) % self.period as u64,
+        );
+        Some(self.infections[rest as usize] + periods * self.infections_per_period)
+    }
+}
+
+// The nodes around the carrier, relative to it.
+fn neighbourhood(world: &World, carrier: &Carrier) -> Vec<usize> {
+    let (row, col) = carrier.position;
+    (-RADIUS..RADIUS + 1)
+        .flat_map(|dy| (-RADIUS..RADIUS + 1).map(move |dx| (row + dy, col + dx)))
+        .map(|position| world.node(position))
+        .collect()
+}
+
+fn offset(from: (i64, i64), to: (i64, i64)) -> (i64, i64) {
+    (to.0 - from.0, to.1 - from.1)
+}
+
+// The shortest period the last few steps repeat with, if any.
+fn find_period(history: &VecDeque<Step>, max_period: usize) -> Option<(usize, (i64, i64))> {
+    let len = history.len();
+    (1..max_period + 1)
+        .take_while(|period| (CONFIRM_PERIODS + 1) * period <= len)
+        .find_map(|period| {
+            let displacement = offset(
+                history[len - 1 - period].position,
+                history[len - 1].position,
+            );
+            let repeats = (len - CONFIRM_PERIODS * period..len).all(|i| {
+                let (now, then) = (&history[i], &history[i - period]);
+                now.node == then.node
+                    && now.direction == then.direction
+                    && offset(then.position, now.position) == displacement
+            });
+            if repeats {
+                Some((period, displacement))
+            } else {
+                None
+            }
+        })
+}
+
+// The range of whole periods `k` for which `node` lies within the
+// neighbourhood the carrier will have after moving `k * displacement` on from
+// `position`, along one axis.
+fn periods_covering(node: i64, position: i64, displacement: i64) -> (f64, f64) {
+    let offset = (node - position) as f64;
+    let step = displacement as f64;
+    let radius = RADIUS as f64;
+    if displacement == 0 {
+        if offset.abs() <= radius {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (f64::INFINITY, f64::NEG_INFINITY)
+        }
+    } else {
+        let (a, b) = ((offset - radius) / step, (offset + radius) / step);
+        (a.min(b), a.max(b))
+    }
+}
+
+// Whether some node that isn't clean lies outside the carrier's neighbourhood
+// now but inside it some whole number of periods on, where it would break the
+// highway.
+fn debris_ahead(world: &World, position: (i64, i64), displacement: (i64, i64)) -> bool {
+    world.nodes.keys().any(|&(row, col)| {
+        let outside = (row - position.0).abs() > RADIUS || (col - position.1).abs() > RADIUS;
+        let rows = periods_covering(row, position.0, displacement.0);
+        let cols = periods_covering(col, position.1, displacement.1);
+        let (from, to) = (rows.0.max(cols.0).max(1.0).ceil(), rows.1.min(cols.1));
+        outside && from <= to
+    })
+}
+
+// Runs the carrier for up to `max_bursts` until its walk repeats with a period
+// of at most `max_period`. A period found from the carrier's moves is only
+// accepted if the carrier stays within its neighbourhood for a period, the
+// neighbourhood looks the same one period later and nothing ahead of it
+// along the way could get in the way.
+pub fn find_highway(
+    world: &mut World,
+    carrier: &mut Carrier,
+    max_bursts: usize,
+    max_period: usize,
+) -> Option<Highway> {
+    let limit = (CONFIRM_PERIODS + 1) * max_period + 1;
+    let mut history = VecDeque::with_capacity(limit);
+    let burst = |world: &mut World, carrier: &mut Carrier, history: &mut VecDeque<Step>| {
+        if history.len() == limit {
+            history.pop_front();
+        }
+        history.push_back(Step {
+            position: carrier.position,
+            direction: carrier.direction,
+            node: world.node(carrier.position),
+        });
+        carrier.burst(world);
+    };
+
+    while carrier.bursts < max_bursts {
+        burst(world, carrier, &mut history);
+        if !carrier.bursts.is_multiple_of(CHECK_INTERVAL) {
+            continue;
+        }
+
+        let (period, displacement) = match find_period(&history, max_period) {
+            Some(found) => found,
+            None => continue,
+        };
+        let (start, position) = (carrier.bursts, carrier.position);
+        let around = neighbourhood(world, carrier);
+        let mut infections = Vec::with_capacity(period);
+        let mut contained = true;
+        for _ in 0..period {
+            infections.push(u64::from(carrier.infections));
+            burst(world, carrier, &mut history);
+            let (dy, dx) = offset(position, carrier.position);
+            contained &= dy.abs() <= RADIUS && dx.abs() <= RADIUS;
+        }
+        if contained
+            && offset(position, carrier.position) == displacement
+            && neighbourhood(world, carrier) == around
+            && !debris_ahead(world, carrier.position, displacement)
+        {
+            return Some(Highway {
+                start,
+                period,
+                displacement,
+                infections_per_period: u64::from(carrier.infections) - infections[0],
+                infections,
+            });
+        }
+    }
+    None
+}
@@ -1,18 +1,144 @@
-extern crate pathfinding;
+mod highway;
+mod render;
 
-use pathfinding::Matrix;
-use pathfinding::Weights;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
 use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq)]
-enum Node {
-    Clean,
-    Weakened,
-    Infected,
-    Flagged
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Turn {
+    Left,
+    Right,
+    Reverse,
+    None,
 }
 
-#[derive(Clone)]
+// What happens when the carrier stands on a node in this state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct NodeState {
+    symbol: char,
+    turn: Turn,
+    next: usize,
+    infected: bool,
+}
+
+// The states a node cycles through, starting from clean at index 0.
+#[derive(Clone, Debug, PartialEq)]
+struct RuleTable {
+    states: Vec<NodeState>,
+}
+
+impl RuleTable {
+    fn virus() -> RuleTable {
+        RuleTable {
+            states: vec![
+                NodeState {
+                    symbol: '.',
+                    turn: Turn::Left,
+                    next: 1,
+                    infected: false,
+                },
+                NodeState {
+                    symbol: '#',
+                    turn: Turn::Right,
+                    next: 0,
+                    infected: true,
+                },
+            ],
+        }
+    }
+
+    fn evolved_virus() -> RuleTable {
+        RuleTable {
+            states: vec![
+                NodeState {
+                    symbol: '.',
+                    turn: Turn::Left,
+                    next: 1,
+                    infected: false,
+                },
+                NodeState {
+                    symbol: 'W',
+                    turn: Turn::None,
+                    next: 2,
+                    infected: false,
+                },
+                NodeState {
+                    symbol: '#',
+                    turn: Turn::Right,
+                    next: 3,
+                    infected: true,
+                },
+                NodeState {
+                    symbol: 'F',
+                    turn: Turn::Reverse,
+                    next: 0,
+                    infected: false,
+                },
+            ],
+        }
+    }
+
+    fn state(&self, symbol: char) -> Option<usize> {
+        self.states.iter().position(|state| state.symbol == symbol)
+    }
+}
+
+// Langton's ant notation, one letter per state: `L`eft, `R`ight, `U`-turn or
+// `N`o turn, each state moving on to the next. A `*` after a letter marks the
+// states that count as infected, otherwise every state but the first does.
+// The first state is shown as `.` and the others by their index.
+impl FromStr for RuleTable {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut states = Vec::new();
+        let mut marked = false;
+        for c in s.trim().chars() {
+            let turn = match c {
+                'L' => Turn::Left,
+                'R' => Turn::Right,
+                'U' => Turn::Reverse,
+                'N' => Turn::None,
+                '*' => {
+                    let state: &mut NodeState = states.last_mut().ok_or(())?;
+                    if state.infected {
+                        return Err(());
+                    }
+                    state.infected = true;
+                    marked = true;
+                    continue;
+                }
+                _ => return Err(()),
+            };
+            let index = states.len();
+            states.push(NodeState {
+                symbol: if index == 0 {
+                    '.'
+                } else {
+                    std::char::from_digit(index as u32, 36).ok_or(())?
+                },
+                turn,
+                next: index + 1,
+                infected: false,
+            });
+        }
+        if states.len() < 2 {
+            return Err(());
+        }
+        let len = states.len();
+        states[len - 1].next = 0;
+        if !marked {
+            states[1..]
+                .iter_mut()
+                .for_each(|state| state.infected = true);
+        }
+        Ok(RuleTable { states })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Direction {
     Up,
     Down,
@@ -20,138 +146,254 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn turn(self, turn: Turn) -> Direction {
+        use Direction::*;
+        match (turn, self) {
+            (Turn::None, _) => self,
+            (Turn::Left, Up) | (Turn::Right, Down) | (Turn::Reverse, Right) => Left,
+            (Turn::Left, Left) | (Turn::Right, Right) | (Turn::Reverse, Up) => Down,
+            (Turn::Left, Down) | (Turn::Right, Up) | (Turn::Reverse, Left) => Right,
+            (Turn::Left, Right) | (Turn::Right, Left) | (Turn::Reverse, Down) => Up,
+        }
+    }
+}
+
+// Nodes are kept by (row, column) relative to the middle of the map, and only
+// while they aren't clean, so carriers can wander without bounds.
 #[derive(Clone)]
-struct Grid {
-    matrix: Matrix<Node>,
-    position: (usize, usize),
-    direction: Direction,
-    infections: u32,
-    evolved: bool,
+struct World {
+    nodes: HashMap<(i64, i64), usize>,
+    rules: RuleTable,
+    infected: usize,
 }
 
-impl Grid {
-    #[allow(dead_code)]
-    fn new(size: usize) -> Grid {
-        Self::from_matrix(Matrix::new_square(size, Node::Clean))
+impl World {
+    fn new(rules: RuleTable) -> World {
+        World {
+            nodes: HashMap::new(),
+            rules,
+            infected: 0,
+        }
     }
 
-    fn from_matrix(matrix: Matrix<Node>) -> Grid {
-        let size = matrix.rows();
-        assert_eq!(size % 2, 1);
-        Grid {
-            matrix: matrix,
-            position: (size / 2, size / 2),
-            direction: Direction::Up,
-            infections: 0,
-            evolved: false,
+    // Parses a map of node symbols from `rules`, centred on (0, 0).
+    fn parse(s: &str, rules: RuleTable) -> Result<World, ()> {
+        let lines = s.lines().map(str::trim).collect::<Vec<_>>();
+        let size = lines.len();
+        if size % 2 != 1 || lines.iter().any(|line| line.chars().count() != size) {
+            return Err(());
         }
+
+        let mut world = World::new(rules);
+        let center = (size / 2) as i64;
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                let state = world.rules.state(c).ok_or(())?;
+                world.set_node((row as i64 - center, col as i64 - center), state);
+            }
+        }
+        Ok(world)
     }
 
-    fn evolve(mut self) -> Self {
-        self.evolved = true;
-        self
+    fn node(&self, position: (i64, i64)) -> usize {
+        self.nodes.get(&position).cloned().unwrap_or(0)
     }
 
-    fn grow(&mut self) {
-        let old_size = self.matrix.rows();
-        if self.position.0 < old_size && self.position.1 < old_size {
-            return;
+    fn set_node(&mut self, position: (i64, i64), state: usize) {
+        let old = if state == 0 {
+            self.nodes.remove(&position)
+        } else {
+            self.nodes.insert(position, state)
+        };
+        if old.is_some_and(|old| self.rules.states[old].infected) {
+            self.infected -= 1;
+        }
+        if self.rules.states[state].infected {
+            self.infected += 1;
         }
+    }
 
-        let mut new_matrix = Matrix::new_square(old_size * 3, Node::Clean);
-        new_matrix.set_slice(&(old_size, old_size), &self.matrix);
-        self.matrix = new_matrix;
-        self.position = (
-            self.position.0.wrapping_add(old_size),
-            self.position.1.wrapping_add(old_size),
-        );
+    // Number of nodes infected right now.
+    fn infected(&self) -> usize {
+        self.infected
+    }
+
+    // Top left and bottom right corners of the smallest box holding the
+    // carriers and every node that isn't clean.
+    fn bounding_box(&self, carriers: &[Carrier]) -> Option<((i64, i64), (i64, i64))> {
+        self.nodes
+            .keys()
+            .chain(carriers.iter().map(|carrier| &carrier.position))
+            .fold(None, |area, &(row, col)| {
+                Some(match area {
+                    Some(((top, left), (bottom, right))) => (
+                        (row.min(top), col.min(left)),
+                        (row.max(bottom), col.max(right)),
+                    ),
+                    None => ((row, col), (row, col)),
+                })
+            })
     }
 }
 
-impl FromStr for Grid {
+impl FromStr for World {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let matrix = Matrix::square_from_vec(
-            s.chars()
-                .filter_map(|c| match c {
-                    '#' => Some(Node::Infected),
-                    '.' => Some(Node::Clean),
-                    _ => None,
-                })
-                .collect(),
-        );
-        Ok(Grid::from_matrix(matrix))
+        World::parse(s, RuleTable::virus())
     }
 }
 
-// TODO: Should use a seperate type for the iterator.
-impl Iterator for Grid {
+// A virus carrier walking a world, along with what it has done to it.
+#[derive(Clone, Debug, PartialEq)]
+struct Carrier {
+    position: (i64, i64),
+    direction: Direction,
+    bursts: usize,
+    infections: u32,
+    cleaned: u32,
+    blocked: u32,
+}
+
+impl Carrier {
+    fn new(position: (i64, i64)) -> Carrier {
+        Carrier {
+            position,
+            direction: Direction::Up,
+            bursts: 0,
+            infections: 0,
+            cleaned: 0,
+            blocked: 0,
+        }
+    }
+
+    // Turns and changes the node under the carrier, without moving on.
+    fn work(&mut self, world: &mut World) {
+        let state = world.rules.states[world.node(self.position)];
+        self.direction = self.direction.turn(state.turn);
+        world.set_node(self.position, state.next);
+        self.bursts += 1;
+        if !state.infected && world.rules.states[state.next].infected {
+            self.infections += 1;
+        }
+        if state.next == 0 {
+            self.cleaned += 1;
+        }
+    }
+
+    fn ahead(&self) -> (i64, i64) {
+        let (row, col) = self.position;
+        match self.direction {
+            Direction::Up => (row - 1, col),
+            Direction::Down => (row + 1, col),
+            Direction::Right => (row, col + 1),
+            Direction::Left => (row, col - 1),
+        }
+    }
+
+    // Runs one burst and returns the infections caused so far.
+    fn burst(&mut self, world: &mut World) -> u32 {
+        self.work(world);
+        self.position = self.ahead();
+        self.infections
+    }
+
+    fn run<'a>(&'a mut self, world: &'a mut World) -> Bursts<'a> {
+        Bursts {
+            carrier: self,
+            world,
+        }
+    }
+}
+
+// Runs a lone carrier, yielding the infections it has caused after each burst.
+struct Bursts<'a> {
+    carrier: &'a mut Carrier,
+    world: &'a mut World,
+}
+
+impl<'a> Iterator for Bursts<'a> {
     type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use Direction::*;
-        self.grow();
-        match (self.matrix[&self.position], self.evolved) {
-            (Node::Clean, false) => {
-                self.infections += 1;
-                self.matrix[&self.position] = Node::Infected;
-                self.direction = match self.direction {
-                    Up => Left,
-                    Left => Down,
-                    Down => Right,
-                    Right => Up,
-                };
-            }
-            (Node::Infected, false) => {
-                self.matrix[&self.position] = Node::Clean;
-                self.direction = match self.direction {
-                    Up => Right,
-                    Right => Down,
-                    Down => Left,
-                    Left => Up,
-                };
-            }
-            (Node::Clean, true) => {
-                self.matrix[&self.position] = Node::Weakened;
-                self.direction = match self.direction {
-                    Up => Left,
-                    Left => Down,
-                    Down => Right,
-                    Right => Up,
-                };
-            }
-            (Node::Infected, true) => {
-                self.matrix[&self.position] = Node::Flagged;
-                self.direction = match self.direction {
-                    Up => Right,
-                    Right => Down,
-                    Down => Left,
-                    Left => Up,
-                };
-            }
-            (Node::Weakened, _) => {
-                self.infections += 1;
-                self.matrix[&self.position] = Node::Infected;
+        Some(self.carrier.burst(self.world))
+    }
+}
+
+// What happens when a carrier moves onto a node another one stands on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Collision {
+    // Both stay there.
+    Share,
+    // The moving carrier stays where it is instead.
+    Block,
+    // Both carriers are destroyed.
+    Destroy,
+}
+
+impl FromStr for Collision {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "share" => Ok(Collision::Share),
+            "block" => Ok(Collision::Block),
+            "destroy" => Ok(Collision::Destroy),
+            _ => Err(()),
+        }
+    }
+}
+
+// Several carriers on one world, taking a burst each in turn every round.
+struct Swarm {
+    carriers: Vec<Carrier>,
+    active: Vec<bool>,
+    collision: Collision,
+    rounds: usize,
+}
+
+impl Swarm {
+    fn new(carriers: Vec<Carrier>, collision: Collision) -> Swarm {
+        Swarm {
+            active: vec![true; carriers.len()],
+            carriers,
+            collision,
+            rounds: 0,
+        }
+    }
+
+    fn round(&mut self, world: &mut World) {
+        for i in 0..self.carriers.len() {
+            if !self.active[i] {
+                continue;
             }
-            (Node::Flagged, _) => {
-                self.matrix[&self.position] = Node::Clean;
-                self.direction = match self.direction {
-                    Up => Down,
-                    Right => Left,
-                    Down => Up,
-                    Left => Right,
-                };
+            self.carriers[i].work(world);
+            let ahead = self.carriers[i].ahead();
+            let others = (0..self.carriers.len())
+                .filter(|&j| j != i && self.active[j] && self.carriers[j].position == ahead)
+                .collect::<Vec<_>>();
+            match self.collision {
+                Collision::Block if !others.is_empty() => self.carriers[i].blocked += 1,
+                Collision::Destroy if !others.is_empty() => {
+                    self.carriers[i].position = ahead;
+                    self.active[i] = false;
+                    others.into_iter().for_each(|j| self.active[j] = false);
+                }
+                _ => self.carriers[i].position = ahead,
             }
         }
-        self.position = match self.direction {
-            Up => (self.position.0.wrapping_sub(1), self.position.1),
-            Down => (self.position.0.wrapping_add(1), self.position.1),
-            Right => (self.position.0, self.position.1.wrapping_add(1)),
-            Left => (self.position.0, self.position.1.wrapping_sub(1)),
-        };
+        self.rounds += 1;
+    }
 
-        Some(self.infections)
+    // The carriers still going.
+    fn active(&self) -> Vec<Carrier> {
+        self.carriers
+            .iter()
+            .zip(&self.active)
+            .filter(|&(_, &active)| active)
+            .map(|(carrier, _)| carrier.clone())
+            .collect()
     }
 }
 
@@ -159,14 +401,100 @@ fn main() {
     let input = include_str!("input.txt");
     let input = input.trim();
 
-    let mut grid: Grid = input.parse().expect("parse");
-    let mut grid2 = grid.clone().evolve();
+    let mut world: World = input.parse().expect("parse");
+    let mut world2 = World::parse(input, RuleTable::evolved_virus()).expect("parse");
 
-    let result = grid.nth(9_999).expect("iter");
+    let result = Carrier::new((0, 0))
+        .run(&mut world)
+        .nth(9_999)
+        .expect("iter");
     println!("Result 1: {}", result);
 
-    let result = grid2.nth(9_999_999).expect("iter");
+    let mut carrier = Carrier::new((0, 0));
+    let result = carrier.run(&mut world2).nth(9_999_999).expect("iter");
     println!("Result 2: {}", result);
+    let ((top, left), (bottom, right)) = world2.bounding_box(&[carrier]).expect("area");
+    println!(
+        "{} nodes infected in a {}x{} area",
+        world2.infected(),
+        bottom - top + 1,
+        right - left + 1
+    );
+
+    let mut world: World = input.parse().expect("parse");
+    match highway::find_highway(&mut world, &mut Carrier::new((0, 0)), 1_000_000, 10_000) {
+        Some(highway) => {
+            println!(
+            "Highway from burst {} every {} bursts moving {:?}, {} infections after 10^12 bursts",
+            highway.start,
+            highway.period,
+            highway.displacement,
+            highway.infections_after(1_000_000_000_000).expect("highway")
+        )
+        }
+        None => println!("No highway within 1000000 bursts"),
+    }
+
+    // Arguments are other rules to run on a clean grid, such as `RLR`,
+    // optionally `--frames DIR` to write out snapshots of the first part and
+    // the swarm as it ends up, and `--collision share|block|destroy` for how the carriers of a swarm on the
+    // puzzle input collide.
+    let mut args = env::args().skip(1);
+    let mut frames = None;
+    let mut rules = None;
+    let mut collision = Collision::Block;
+    while let Some(arg) = args.next() {
+        if arg == "--frames" {
+            frames = args.next();
+        } else if arg == "--collision" {
+            collision = args
+                .next()
+                .and_then(|arg| arg.parse().ok())
+                .expect("collision");
+        } else {
+            rules = Some(arg.parse().expect("rules"));
+        }
+    }
+
+    let mut world: World = input.parse().expect("parse");
+    let carriers = vec![(0, 0), (0, 8), (8, 0), (8, 8)]
+        .into_iter()
+        .map(Carrier::new)
+        .collect();
+    let mut swarm = Swarm::new(carriers, collision);
+    (0..10_000).for_each(|_| swarm.round(&mut world));
+    for (i, carrier) in swarm.carriers.iter().enumerate() {
+        println!(
+            "Carrier {}: {} infections, {} cleaned, blocked {} times",
+            i, carrier.infections, carrier.cleaned, carrier.blocked
+        );
+    }
+    println!(
+        "{} of {} carriers left after {} rounds",
+        swarm.active().len(),
+        swarm.carriers.len(),
+        swarm.rounds
+    );
+    if let Some(ref dir) = frames {
+        render::write_swarm(&Path::new(dir).join("swarm.txt"), &world, &swarm.carriers)
+            .expect("swarm");
+    }
+
+    if let Some(rules) = rules {
+        let mut world = World::new(rules);
+        Carrier::new((0, 0))
+            .run(&mut world)
+            .nth(9_999)
+            .expect("iter");
+        println!("{} nodes infected after 10000 bursts", world.infected());
+    }
+
+    if let Some(dir) = frames {
+        let mut world: World = input.parse().expect("parse");
+        let mut carrier = Carrier::new((0, 0));
+        render::write_frames(Path::new(&dir), &mut world, &mut carrier, 100, 101, 4)
+            .expect("frames");
+    }
 }
 
 #[cfg(test)]
@@ -184,9 +512,11 @@ mod tests {
                      .........\n\
                      .........\n\
                      .........";
-        let mut grid: Grid = input.parse().unwrap();
-        assert_eq!(grid.nth(69).unwrap(), 41);
-        assert_eq!(grid.nth(9_929).unwrap(), 5587);
+        let mut world: World = input.parse().unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        let mut bursts = carrier.run(&mut world);
+        assert_eq!(bursts.nth(69).unwrap(), 41);
+        assert_eq!(bursts.nth(9_929).unwrap(), 5587);
     }
 
     #[test]
@@ -200,8 +530,287 @@ mod tests {
                      .........\n\
                      .........\n\
                      .........";
-        let mut grid: Grid = input.parse::<Grid>().unwrap().evolve();
-        assert_eq!(grid.nth(99).unwrap(), 26);
-        assert_eq!(grid.nth(9_999_899).unwrap(), 2_511_944);
+        let mut world = World::parse(input, RuleTable::evolved_virus()).unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        let mut bursts = carrier.run(&mut world);
+        assert_eq!(bursts.nth(99).unwrap(), 26);
+        assert_eq!(bursts.nth(9_999_899).unwrap(), 2_511_944);
+    }
+
+    #[test]
+    fn sparse_grid_test() {
+        let input = "..#\n\
+                     #..\n\
+                     ...";
+        let mut world: World = input.parse().unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        assert_eq!(world.infected(), 2);
+        assert_eq!(world.bounding_box(&[]), Some(((-1, -1), (0, 1))));
+
+        // Infects the middle and turns left, then cleans the node there.
+        assert_eq!(carrier.burst(&mut world), 1);
+        assert_eq!(world.infected(), 3);
+        assert_eq!(carrier.burst(&mut world), 1);
+        assert_eq!(world.infected(), 2);
+        assert_eq!(carrier.cleaned, 1);
+        assert_eq!(world.bounding_box(&[]), Some(((-1, 0), (0, 1))));
+        assert_eq!(world.bounding_box(&[carrier]), Some(((-1, -1), (0, 1))));
+
+        let mut world = World::new(RuleTable::virus());
+        assert_eq!(world.bounding_box(&[]), None);
+        Carrier::new((0, 0)).run(&mut world).nth(999).unwrap();
+        let ((top, left), (bottom, right)) = world.bounding_box(&[]).unwrap();
+        assert!(bottom - top > 3 && right - left > 3);
+        assert_eq!(world.infected(), world.nodes.len());
+
+        assert!("..\n..".parse::<World>().is_err());
+        assert!("...\n.x.\n...".parse::<World>().is_err());
+    }
+
+    #[test]
+    fn swarm_test() {
+        // A lone carrier in a swarm does what it does by itself.
+        let input = include_str!("input.txt").trim();
+        let mut world: World = input.parse().unwrap();
+        let mut swarm = Swarm::new(vec![Carrier::new((0, 0))], Collision::Share);
+        (0..10_000).for_each(|_| swarm.round(&mut world));
+        assert_eq!(swarm.carriers[0].infections, 5450);
+
+        // On clean nodes both carriers turn left, the first one towards the
+        // second.
+        let carriers = || vec![Carrier::new((0, 0)), Carrier::new((0, -1))];
+        let mut world = World::new(RuleTable::virus());
+        let mut swarm = Swarm::new(carriers(), Collision::Block);
+        swarm.round(&mut world);
+        assert_eq!(swarm.carriers[0].blocked, 1);
+        assert_eq!(swarm.carriers[0].position, (0, 0));
+        assert_eq!(swarm.carriers[1].position, (0, -2));
+        assert_eq!(world.infected(), 2);
+
+        let mut world = World::new(RuleTable::virus());
+        let mut swarm = Swarm::new(carriers(), Collision::Share);
+        swarm.round(&mut world);
+        assert_eq!(swarm.carriers[0].position, (0, -1));
+        assert_eq!(swarm.active().len(), 2);
+
+        let mut world = World::new(RuleTable::virus());
+        let carriers = vec![
+            Carrier::new((0, 0)),
+            Carrier::new((0, -1)),
+            Carrier::new((5, 5)),
+        ];
+        let mut swarm = Swarm::new(carriers, Collision::Destroy);
+        swarm.round(&mut world);
+        assert_eq!(swarm.active(), vec![swarm.carriers[2].clone()]);
+        swarm.round(&mut world);
+        assert_eq!(swarm.carriers[0].bursts, 1);
+        assert_eq!(swarm.carriers[2].bursts, 2);
+        assert_eq!(swarm.rounds, 2);
+    }
+
+    #[test]
+    fn render_test() {
+        let area = ((-4, -4), (3, 4));
+        let mut world: World = "..#\n#..\n...".parse().unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        let snapshots = render::snapshots(&mut world, &mut carrier, 1, 3)
+            .iter()
+            .map(|(world, carrier)| render::render_text(world, carrier, area))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            snapshots,
+            vec![
+                ". . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . # . . .\n\
+                 . . . #[.]. . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .",
+                ". . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . # . . .\n\
+                 . . .[#]# . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .",
+                ". . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . .[.]. # . . .\n\
+                 . . . . # . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .\n\
+                 . . . . . . . . .",
+            ]
+        );
+
+        carrier.run(&mut world).nth(3).unwrap();
+        assert_eq!(
+            render::render_text(&world, &carrier, area),
+            ". . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . #[#]. # . . .\n\
+             . . # # # . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . ."
+        );
+        carrier.run(&mut world).nth(63).unwrap();
+        assert_eq!(
+            render::render_text(&world, &carrier, area),
+            ". . . . . # # . .\n\
+             . . . . # . . # .\n\
+             . . . # . . . . #\n\
+             . . # . #[.]. . #\n\
+             . . # . # . . # .\n\
+             . . . . . # # . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . ."
+        );
+
+        let mut world = World::parse("..#\n#..\n...", RuleTable::evolved_virus()).unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        carrier.run(&mut world).nth(1).unwrap();
+        assert_eq!(
+            render::render_text(&world, &carrier, area),
+            ". . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . .[.]. # . . .\n\
+             . . . F W . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . .\n\
+             . . . . . . . . ."
+        );
+
+        let mut ppm = Vec::new();
+        render::write_ppm(&mut ppm, &world, &[carrier], ((-1, -1), (-1, 0)), 3).unwrap();
+        assert_eq!(&ppm[..11], b"P6\n6 3\n255\n");
+        assert_eq!(ppm.len(), 11 + 6 * 3 * 3);
+        // The carrier faces up, so its top edge is yellow.
+        assert_eq!(&ppm[11..14], &[255, 220, 0]);
+        assert_eq!(&ppm[11 + 18 + 3..11 + 18 + 6], &[220, 0, 0]);
+        assert_eq!(&ppm[11 + 18 + 9..11 + 18 + 12], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn render_carriers_test() {
+        let world: World = "#..\n...\n...".parse().unwrap();
+        let mut carriers = vec![Carrier::new((0, -1)), Carrier::new((0, 0))];
+        carriers[1].direction = Direction::Right;
+        assert_eq!(
+            render::render_carriers_text(&world, &carriers, ((-1, -1), (0, 1))),
+            "# . .\n^.>. ."
+        );
+
+        carriers.push(Carrier::new((0, 0)));
+        assert_eq!(
+            render::render_carriers_text(&world, &carriers, ((0, -1), (0, 1))),
+            "^.*. ."
+        );
+    }
+
+    #[test]
+    fn highway_test() {
+        // Langton's ant builds its highway after about 10,000 steps.
+        let mut ant = World::new("RL".parse().unwrap());
+        let highway =
+            highway::find_highway(&mut ant, &mut Carrier::new((0, 0)), 100_000, 1_000).unwrap();
+        assert!(highway.start > 9_000 && highway.start < 20_000);
+        assert_eq!(highway.period, 104);
+        assert_eq!(
+            (highway.displacement.0.abs(), highway.displacement.1.abs()),
+            (2, 2)
+        );
+        assert_eq!(highway.infections_after(highway.start as u64 - 1), None);
+
+        let mut ant = World::new("RL".parse().unwrap());
+        let mut carrier = Carrier::new((0, 0));
+        for bursts in (highway.start + 1..30_000).step_by(997) {
+            let skip = bursts - carrier.bursts - 1;
+            let infections = carrier.run(&mut ant).nth(skip).unwrap();
+            assert_eq!(
+                highway.infections_after(bursts as u64),
+                Some(u64::from(infections))
+            );
+        }
+
+        // A node left ahead of the highway breaks it, so it isn't one yet.
+        let mut ant = World::new("RL".parse().unwrap());
+        let mut carrier = Carrier::new((0, 0));
+        let found = highway::find_highway(&mut ant, &mut carrier, 100_000, 1_000).unwrap();
+        let (row, col) = carrier.position;
+        let (dy, dx) = found.displacement;
+        let mut ant = World::new("RL".parse().unwrap());
+        ant.set_node((row + 40 * dy, col + 40 * dx + 1), 1);
+        assert_eq!(
+            highway::find_highway(
+                &mut ant,
+                &mut Carrier::new((0, 0)),
+                found.start + 3_000,
+                1_000
+            ),
+            None
+        );
+
+        let input = include_str!("input.txt").trim();
+        let mut world: World = input.parse().unwrap();
+        let highway =
+            highway::find_highway(&mut world, &mut Carrier::new((0, 0)), 100_000, 1_000).unwrap();
+        assert_eq!(highway.infections_after(10_000), Some(5450));
+
+        let mut virus = World::parse("..#\n#..\n...", RuleTable::evolved_virus()).unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        assert_eq!(
+            highway::find_highway(&mut virus, &mut carrier, 10_000, 100),
+            None
+        );
+    }
+
+    #[test]
+    fn rule_table_test() {
+        // Same rules, only the symbols differ.
+        let actions = |rules: RuleTable| {
+            rules
+                .states
+                .iter()
+                .map(|state| (state.turn, state.next, state.infected))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(actions("LR".parse().unwrap()), actions(RuleTable::virus()));
+        assert_eq!(
+            actions("LNR*U".parse().unwrap()),
+            actions(RuleTable::evolved_virus())
+        );
+        assert!("L".parse::<RuleTable>().is_err());
+        assert!("*LR".parse::<RuleTable>().is_err());
+        assert!("LXR".parse::<RuleTable>().is_err());
+
+        // Langton's ant heads off on its highway after about 10,000 steps, with
+        // 12 more nodes flipped on every 104 step cycle.
+        let mut ant = World::new("RL".parse().unwrap());
+        let mut carrier = Carrier::new((0, 0));
+        carrier.run(&mut ant).nth(11_999).unwrap();
+        let before = ant.infected();
+        carrier.run(&mut ant).nth(103).unwrap();
+        assert_eq!(ant.infected(), before + 12);
+
+        // Only turning a node infected is an infection, not moving it from one
+        // infected state to the next.
+        let mut world = World::parse("...\n...\n...", "RL*L*".parse().unwrap()).unwrap();
+        let mut carrier = Carrier::new((0, 0));
+        for _ in 0..3 {
+            carrier.work(&mut world);
+        }
+        assert_eq!(world.node((0, 0)), 0);
+        assert_eq!((carrier.infections, carrier.cleaned), (1, 1));
+
+        let mut rlr = World::new("RLR".parse().unwrap());
+        Carrier::new((0, 0)).run(&mut rlr).nth(999).unwrap();
+        assert!(rlr.nodes.values().any(|&state| state == 2));
     }
 }
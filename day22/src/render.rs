@@ -0,0 +1,209 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::slice;
+
+use super::{Carrier, Direction, World};
+
+type Area = ((i64, i64), (i64, i64));
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+fn direction_glyph(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '^',
+        Direction::Down => 'v',
+        Direction::Left => '<',
+        Direction::Right => '>',
+    }
+}
+
+// Renders `area` of the world as in the puzzle, with the carrier's node in
+// brackets.
+pub fn render_text(world: &World, carrier: &Carrier, area: Area) -> String {
+    let ((top, left), (bottom, right)) = area;
+    (top..bottom + 1)
+        .map(|row| {
+            let mut line = String::new();
+            for col in left..right + 1 {
+                if carrier.position == (row, col) {
+                    line.push('[');
+                } else if carrier.position == (row, col - 1) {
+                    line.push(']');
+                } else if col > left {
+                    line.push(' ');
+                }
+                line.push(world.rules.states[world.node((row, col))].symbol);
+            }
+            if carrier.position == (row, right) {
+                line.push(']');
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Renders `area` of the world for several carriers, which brackets can't keep
+// apart when they are next to each other. Instead a carrier's node is preceded
+// by the way it faces, or by `*` if more than one carrier is on it.
+pub fn render_carriers_text(world: &World, carriers: &[Carrier], area: Area) -> String {
+    let ((top, left), (bottom, right)) = area;
+    (top..bottom + 1)
+        .map(|row| {
+            let mut line = String::new();
+            for col in left..right + 1 {
+                let mut here = carriers
+                    .iter()
+                    .filter(|carrier| carrier.position == (row, col));
+                match (here.next(), here.next()) {
+                    (Some(_), Some(_)) => line.push('*'),
+                    (Some(carrier), None) => line.push(direction_glyph(carrier.direction)),
+                    _ if col > left => line.push(' '),
+                    _ => {}
+                }
+                line.push(world.rules.states[world.node((row, col))].symbol);
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Copies of the world and carrier every `every` bursts, starting with them as
+// they are now.
+pub fn snapshots(
+    world: &mut World,
+    carrier: &mut Carrier,
+    every: usize,
+    count: usize,
+) -> Vec<(World, Carrier)> {
+    let mut snapshots = vec![(world.clone(), carrier.clone())];
+    for _ in 1..count {
+        carrier.run(world).take(every).for_each(drop);
+        snapshots.push((world.clone(), carrier.clone()));
+    }
+    snapshots
+}
+
+fn union(areas: &[Area]) -> Area {
+    areas.iter().skip(1).fold(
+        areas[0],
+        |((top, left), (bottom, right)), &((t, l), (b, r))| {
+            ((top.min(t), left.min(l)), (bottom.max(b), right.max(r)))
+        },
+    )
+}
+
+// Colour of a node: clean is white, infected black and anything in between
+// a shade of grey.
+fn node_colour(world: &World, state: usize) -> [u8; 3] {
+    let states = world.rules.states.len();
+    let shade = if world.rules.states[state].infected {
+        0
+    } else {
+        255 - (state * 192 / states) as u8
+    };
+    [shade, shade, shade]
+}
+
+// Writes a binary PPM of `area` with every node drawn `scale` pixels wide. The
+// carriers' nodes are red, with a yellow edge on the side they are facing.
+pub fn write_ppm<W: Write>(
+    writer: &mut W,
+    world: &World,
+    carriers: &[Carrier],
+    area: Area,
+    scale: usize,
+) -> io::Result<()> {
+    let ((top, left), (bottom, right)) = area;
+    let width = (right - left + 1) as usize * scale;
+    let height = (bottom - top + 1) as usize * scale;
+    let edge = (scale / 3).max(1);
+    writeln!(writer, "P6\n{} {}\n255", width, height)?;
+    for y in 0..height {
+        let row = top + (y / scale) as i64;
+        let mut line = Vec::with_capacity(width * 3);
+        for x in 0..width {
+            let col = left + (x / scale) as i64;
+            let carrier = carriers
+                .iter()
+                .find(|carrier| carrier.position == (row, col));
+            let colour = if let Some(carrier) = carrier {
+                let (dy, dx) = (y % scale, x % scale);
+                let facing = match carrier.direction {
+                    Direction::Up => dy < edge,
+                    Direction::Down => dy >= scale - edge,
+                    Direction::Left => dx < edge,
+                    Direction::Right => dx >= scale - edge,
+                };
+                if facing {
+                    [255, 220, 0]
+                } else {
+                    [220, 0, 0]
+                }
+            } else {
+                node_colour(world, world.node((row, col)))
+            };
+            line.extend(&colour);
+        }
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
+// Writes `count` snapshots taken every `every` bursts, all covering the same
+// area, to `frames.txt` and as `frame-000.ppm` onwards.
+pub fn write_frames(
+    dir: &Path,
+    world: &mut World,
+    carrier: &mut Carrier,
+    every: usize,
+    count: usize,
+    scale: usize,
+) -> io::Result<()> {
+    let start = carrier.bursts;
+    let snapshots = snapshots(world, carrier, every, count);
+    let area = union(
+        &snapshots
+            .iter()
+            .map(|(world, carrier)| world.bounding_box(slice::from_ref(carrier)).unwrap())
+            .collect::<Vec<_>>(),
+    );
+
+    let mut text = BufWriter::new(File::create(dir.join("frames.txt"))?);
+    for (i, (world, carrier)) in snapshots.iter().enumerate() {
+        if i > 0 {
+            writeln!(text)?;
+        }
+        writeln!(
+            text,
+            "Burst {}: carrier at {:?} facing {}, {} infections",
+            start + i * every,
+            carrier.position,
+            direction_name(carrier.direction),
+            carrier.infections
+        )?;
+        writeln!(text, "{}", render_text(world, carrier, area))?;
+        let mut frame = BufWriter::new(File::create(dir.join(format!("frame-{:03}.ppm", i)))?);
+        write_ppm(&mut frame, world, slice::from_ref(carrier), area, scale)?;
+    }
+    Ok(())
+}
+
+// Writes the world and every carrier on it to `path`, as `render_carriers_text`
+// draws them.
+pub fn write_swarm(path: &Path, world: &World, carriers: &[Carrier]) -> io::Result<()> {
+    let mut text = BufWriter::new(File::create(path)?);
+    if let Some(area) = world.bounding_box(carriers) {
+        writeln!(text, "{}", render_carriers_text(world, carriers, area))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::{strength, Connector};
+
+// A connector as placed in a bridge, joined on `port` and leaving its other
+// port free for the next one.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Link {
+    pub connector: Connector,
+    pub port: u32,
+}
+
+// The connectors of a bridge, in order from port 0.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bridge {
+    pub links: Vec<Link>,
+}
+
+impl Bridge {
+    pub fn connectors(&self) -> Vec<Connector> {
+        self.links
+            .iter()
+            .map(|link| link.connector.clone())
+            .collect()
+    }
+
+    pub fn strength(&self) -> u32 {
+        strength(&self.connectors())
+    }
+}
+
+// Written as in the puzzle, e.g. `0/1--10/1--9/10`.
+impl fmt::Display for Bridge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, link) in self.links.iter().enumerate() {
+            if i > 0 {
+                write!(f, "--")?;
+            }
+            write!(f, "{}/{}", link.connector.0, link.connector.1)?;
+        }
+        Ok(())
+    }
+}
+
+// The best bridges found so far, best first. When `exact` is set the bridges
+// tying the last of the `limit` best are kept too, and equal scores are
+// ordered by bridge, so the result does not depend on the search order.
+pub struct Ranking<T> {
+    limit: usize,
+    exact: bool,
+    bridges: Vec<(T, Bridge)>,
+}
+
+impl<T: Ord> Ranking<T> {
+    pub fn new(limit: usize, exact: bool) -> Self {
+        Ranking {
+            limit,
+            exact,
+            bridges: Vec::new(),
+        }
+    }
+
+    // Whether no bridge scoring `score` or less could get in any more.
+    pub fn excludes(&self, score: &T) -> bool {
+        if self.limit == 0 {
+            return true;
+        }
+        if self.bridges.len() < self.limit {
+            return false;
+        }
+        let worst = &self.bridges[self.limit - 1].0;
+        if self.exact {
+            score < worst
+        } else {
+            score <= worst
+        }
+    }
+
+    fn order(&self, a: &(T, Bridge), b: &(T, Bridge)) -> Ordering {
+        let ord = b.0.cmp(&a.0);
+        if self.exact {
+            ord.then_with(|| a.1.cmp(&b.1))
+        } else {
+            ord
+        }
+    }
+
+    // Adds the bridge made by `bridge` if it scores well enough.
+    pub fn offer<F: FnOnce() -> Bridge>(&mut self, score: T, bridge: F) {
+        if self.excludes(&score) {
+            return;
+        }
+        let entry = (score, bridge());
+        let at = self
+            .bridges
+            .iter()
+            .position(|other| self.order(&entry, other) == Ordering::Less)
+            .unwrap_or(self.bridges.len());
+        self.bridges.insert(at, entry);
+        if self.bridges.len() > self.limit {
+            let cutoff = &self.bridges[self.limit - 1].0;
+            let keep = if self.exact {
+                self.limit
+                    + self.bridges[self.limit..]
+                        .iter()
+                        .take_while(|(score, _)| score == cutoff)
+                        .count()
+            } else {
+                self.limit
+            };
+            self.bridges.truncate(keep);
+        }
+    }
+
+    // Adds the bridges of another ranking kept the same way.
+    pub fn merge(&mut self, other: Ranking<T>) {
+        for (score, bridge) in other.bridges {
+            self.offer(score, || bridge);
+        }
+    }
+
+    pub fn into_bridges(self) -> Vec<(T, Bridge)> {
+        self.bridges
+    }
+}
@@ -1,16 +1,42 @@
 #[macro_use]
 extern crate failure;
 
+mod bridge;
+mod report;
+
+use bridge::{Bridge, Link, Ranking};
 use failure::Error;
-use std::collections::VecDeque;
-use std::cmp::Ordering;
+use report::{write_dot, Report};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::thread;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Connector(u32, u32);
 
 impl Connector {
+    fn is_loop(&self) -> bool {
+        self.0 == self.1
+    }
+
+    // The same connector with its smaller port first.
+    fn normalized(&self) -> Connector {
+        Connector(self.0.min(self.1), self.0.max(self.1))
+    }
+
+    // The port left free when joining this connector on `port`. A loop joined
+    // on its port leaves that same port free.
     fn connects(&self, port: u32) -> Option<u32> {
-        if port == self.0 {
+        if self.is_loop() {
+            if port == self.0 {
+                Some(port)
+            } else {
+                None
+            }
+        } else if port == self.0 {
             Some(self.1)
         } else if port == self.1 {
             Some(self.0)
@@ -20,30 +46,362 @@ impl Connector {
     }
 }
 
+// A bridge's score. It may only depend on which connectors are used, not on
+// their order, as bridges reaching the same port with the same connectors are
+// only scored once.
 trait FromConnectors {
     fn from_connectors(connectors: &[Connector]) -> Self;
+
+    // The best score any bridge extending `connectors` with at most
+    // `remaining` more connectors, `remaining_strength` strong in total, could
+    // get. Without one every extension is searched.
+    fn upper_bound(
+        _connectors: &[Connector],
+        _remaining: usize,
+        _remaining_strength: u32,
+    ) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+fn strength(connectors: &[Connector]) -> u32 {
+    connectors
+        .iter()
+        .map(|connector| connector.0 + connector.1)
+        .sum()
+}
+
+// How many states a search remembers before forgetting them all, to keep
+// its memory bounded.
+const SEEN_LIMIT: usize = 1 << 20;
+
+// Indices of the bits set in `mask`, lowest first.
+fn bits(mut mask: u128) -> impl Iterator<Item = usize> {
+    (0..mask.count_ones()).map(move |_| {
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        bit
+    })
+}
+
+// The connectors as the search sees them: sorted strongest first, with ports
+// numbered densely and the connectors fitting each port kept as a bitmask.
+struct Graph {
+    connectors: Vec<Connector>,
+    ends: Vec<(usize, usize)>,
+    weights: Vec<u32>,
+    fitting: Vec<u128>,
+    loops: u128,
+    ports: Vec<u32>,
+    start: usize,
+}
+
+impl Graph {
+    fn new(connectors: &[Connector]) -> Result<Graph, Error> {
+        if connectors.len() > 128 {
+            bail!("At most 128 connectors are supported");
+        }
+        let mut connectors = connectors.to_vec();
+        connectors.sort_by_key(|connector| Reverse(connector.0 + connector.1));
+        let mut ports = connectors
+            .iter()
+            .flat_map(|connector| vec![connector.0, connector.1])
+            .chain(Some(0))
+            .collect::<Vec<_>>();
+        ports.sort_unstable();
+        ports.dedup();
+        let index = |port| ports.binary_search(&port).unwrap();
+
+        let ends = connectors
+            .iter()
+            .map(|connector| (index(connector.0), index(connector.1)))
+            .collect::<Vec<_>>();
+        let fitting = ports
+            .iter()
+            .map(|&port| {
+                (0..connectors.len())
+                    .filter(|&i| connectors[i].connects(port).is_some())
+                    .fold(0, |fitting, i| fitting | 1 << i)
+            })
+            .collect();
+        let loops = (0..connectors.len())
+            .filter(|&i| connectors[i].is_loop())
+            .fold(0, |loops, i| loops | 1 << i);
+        Ok(Graph {
+            weights: connectors.iter().map(|c| c.0 + c.1).collect(),
+            connectors,
+            ends,
+            fitting,
+            loops,
+            start: index(0),
+            ports,
+        })
+    }
+
+    fn other_end(&self, i: usize, port: usize) -> usize {
+        let (a, b) = self.ends[i];
+        if a == port {
+            b
+        } else {
+            a
+        }
+    }
+
+    fn weight(&self, mask: u128) -> u32 {
+        bits(mask).map(|i| self.weights[i]).sum()
+    }
 }
 
-fn find_best<T: FromConnectors + Ord>(
-    last_port: u32,
-    mut available: &mut VecDeque<Connector>,
-    mut used: &mut Vec<Connector>,
-) -> T {
-    let best = T::from_connectors(used);
-
-    (0..available.len())
-        .filter_map(|i| {
-            if let Some(next_port) = available[i].connects(last_port) {
-                used.push(available.remove(i).unwrap());
-                let best = find_best(next_port, &mut available, &mut used);
-                available.push_front(used.pop().unwrap());
-                Some(best)
+// Depth first search over bridges, ranking the ones it finds by itself. The
+// bound on what a (connectors used, last port) state can still add is
+// remembered, and unless the ranking is exact so is which states were
+// already seen, as reordering the same connectors cannot improve the score.
+struct Search<'a, T: 'a> {
+    graph: &'a Graph,
+    ranking: Ranking<T>,
+    bounded: bool,
+    memo: bool,
+    seen: HashSet<(u128, usize)>,
+    bounds: HashMap<(u128, usize), (usize, u32)>,
+    used: Vec<Connector>,
+    joined: Vec<u32>,
+    order: Vec<usize>,
+    low: Vec<usize>,
+}
+
+impl<'a, T: FromConnectors + Ord> Search<'a, T> {
+    fn new(graph: &'a Graph, limit: usize, exact: bool) -> Self {
+        Search {
+            graph,
+            ranking: Ranking::new(limit, exact),
+            bounded: T::upper_bound(&[], 0, 0).is_some(),
+            memo: !exact,
+            seen: HashSet::new(),
+            bounds: HashMap::new(),
+            used: Vec::new(),
+            joined: Vec::new(),
+            order: vec![usize::MAX; graph.fitting.len()],
+            low: vec![0; graph.fitting.len()],
+        }
+    }
+
+    fn bridge(&self) -> Bridge {
+        Bridge {
+            links: self
+                .used
+                .iter()
+                .zip(&self.joined)
+                .map(|(connector, &port)| Link {
+                    connector: connector.clone(),
+                    port,
+                })
+                .collect(),
+        }
+    }
+
+    fn push(&mut self, i: usize, port: usize) {
+        self.used.push(self.graph.connectors[i].clone());
+        self.joined.push(self.graph.ports[port]);
+    }
+
+    fn pop(&mut self) {
+        self.used.pop();
+        self.joined.pop();
+    }
+
+    // How many more connectors, and how much more strength, a bridge at
+    // `port` could still add using the unused connectors reachable from it.
+    fn remaining(&mut self, port: usize, mask: u128) -> (usize, u32) {
+        self.order.iter_mut().for_each(|order| *order = usize::MAX);
+        let mut cut = 0;
+        self.find_cut_connectors(port, usize::MAX, mask, &mut 0, &mut cut);
+        let (tree_count, tree_strength) = self.tree_bound(port, cut, &mut mask.clone());
+        let (count, strength) = self.parity_bound(port, mask);
+        (count.min(tree_count), strength.min(tree_strength))
+    }
+
+    // Connectors whose removal would split the unused connectors reachable
+    // from `port`, by Tarjan's bridge finding algorithm. Visited ports get an
+    // order.
+    fn find_cut_connectors(
+        &mut self,
+        port: usize,
+        via: usize,
+        mask: u128,
+        counter: &mut usize,
+        cut: &mut u128,
+    ) {
+        self.order[port] = *counter;
+        self.low[port] = *counter;
+        *counter += 1;
+        for i in bits(self.graph.fitting[port] & !mask & !self.graph.loops) {
+            if i == via {
+                continue;
+            }
+            let to = self.graph.other_end(i, port);
+            if self.order[to] == usize::MAX {
+                self.find_cut_connectors(to, i, mask, counter, cut);
+                self.low[port] = self.low[port].min(self.low[to]);
+                if self.low[to] > self.order[port] {
+                    *cut |= 1 << i;
+                }
             } else {
-                None
+                self.low[port] = self.low[port].min(self.order[to]);
             }
-        })
-        .max()
-        .unwrap_or(best)
+        }
+    }
+
+    // A bridge crosses each cut connector at most once, so it can only use the
+    // connectors of the groups along one path of cut connectors.
+    fn tree_bound(&self, port: usize, cut: u128, counted: &mut u128) -> (usize, u32) {
+        let (mut group, mut children) = (0u128, Vec::new());
+        let mut stack = vec![port];
+        while let Some(from) = stack.pop() {
+            for i in bits(self.graph.fitting[from] & !*counted) {
+                *counted |= 1 << i;
+                let to = self.graph.other_end(i, from);
+                if cut & 1 << i != 0 {
+                    children.push((i, to));
+                } else {
+                    group |= 1 << i;
+                    stack.push(to);
+                }
+            }
+        }
+        let (mut count, mut strength) = (0, 0);
+        for (i, to) in children {
+            let (c, s) = self.tree_bound(to, cut, counted);
+            count = count.max(c + 1);
+            strength = strength.max(s + self.graph.weights[i]);
+        }
+        (
+            group.count_ones() as usize + count,
+            self.graph.weight(group) + strength,
+        )
+    }
+
+    // A bridge only passes through ports fitting an even number of the
+    // connectors it uses, so of the reachable ports fitting an odd number of
+    // unused connectors, all but one leave at least one of them unused. Each
+    // unused connector fixes at most two ports.
+    fn parity_bound(&self, port: usize, mask: u128) -> (usize, u32) {
+        let reached = (0..self.order.len()).filter(|&p| self.order[p] != usize::MAX);
+        let unused = reached
+            .clone()
+            .fold(0, |unused, p| unused | self.graph.fitting[p] & !mask);
+        let mut odd = reached
+            .filter(|&p| p != port)
+            .map(|p| self.graph.fitting[p] & unused & !self.graph.loops)
+            .filter(|fitting| fitting.count_ones() % 2 == 1)
+            .map(|fitting| self.graph.weights[127 - fitting.leading_zeros() as usize])
+            .collect::<Vec<_>>();
+        odd.sort_unstable();
+        odd.pop();
+        (
+            unused.count_ones() as usize - odd.len().div_ceil(2),
+            self.graph.weight(unused) - odd.iter().sum::<u32>() / 2,
+        )
+    }
+
+    fn visit(&mut self, port: usize, mask: u128) {
+        if self.memo {
+            if self.seen.len() >= SEEN_LIMIT {
+                self.seen.clear();
+            }
+            if !self.seen.insert((mask, port)) {
+                return;
+            }
+        }
+        let score = T::from_connectors(&self.used);
+        if !self.ranking.excludes(&score) {
+            let bridge = self.bridge();
+            self.ranking.offer(score, || bridge);
+        }
+        if self.bounded {
+            let (remaining, remaining_strength) = match self.bounds.get(&(mask, port)) {
+                Some(&bound) => bound,
+                None => {
+                    if self.bounds.len() >= SEEN_LIMIT {
+                        self.bounds.clear();
+                    }
+                    let bound = self.remaining(port, mask);
+                    self.bounds.insert((mask, port), bound);
+                    bound
+                }
+            };
+            if let Some(bound) = T::upper_bound(&self.used, remaining, remaining_strength) {
+                if self.ranking.excludes(&bound) {
+                    return;
+                }
+            }
+        }
+
+        for i in bits(self.graph.fitting[port] & !mask) {
+            self.push(i, port);
+            self.visit(self.graph.other_end(i, port), mask | 1 << i);
+            self.pop();
+        }
+    }
+}
+
+// Ranks the bridges starting from port 0, searching the bridges starting with
+// each connector that fits on a separate thread and merging what they found.
+fn rank<T: FromConnectors + Ord + Send>(
+    connectors: &[Connector],
+    limit: usize,
+    exact: bool,
+) -> Result<Vec<(T, Bridge)>, Error> {
+    let graph = Graph::new(connectors)?;
+    let mut ranking = Ranking::new(limit, exact);
+    ranking.offer(T::from_connectors(&[]), Bridge::default);
+    let rankings = thread::scope(|scope| {
+        let searches = bits(graph.fitting[graph.start])
+            .map(|i| {
+                let graph = &graph;
+                scope.spawn(move || {
+                    let mut search = Search::new(graph, limit, exact);
+                    search.push(i, graph.start);
+                    search.visit(graph.other_end(i, graph.start), 1 << i);
+                    search.ranking
+                })
+            })
+            .collect::<Vec<_>>();
+        searches
+            .into_iter()
+            .map(|search| search.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    for other in rankings {
+        ranking.merge(other);
+    }
+    Ok(ranking.into_bridges())
+}
+
+// One of the best bridges and its score.
+fn best_bridge<T: FromConnectors + Ord + Send>(
+    connectors: &[Connector],
+) -> Result<(T, Bridge), Error> {
+    Ok(rank(connectors, 1, false)?.remove(0))
+}
+
+// All the bridges tying for the best score, in order.
+fn best_bridges<T: FromConnectors + Ord + Send>(
+    connectors: &[Connector],
+) -> Result<Vec<(T, Bridge)>, Error> {
+    rank(connectors, 1, true)
+}
+
+// The `k` best bridges, ties broken by the order of their connectors.
+fn top_bridges<T: FromConnectors + Ord + Send>(
+    connectors: &[Connector],
+    k: usize,
+) -> Result<Vec<(T, Bridge)>, Error> {
+    let mut bridges = rank(connectors, k, true)?;
+    bridges.truncate(k);
+    Ok(bridges)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -51,12 +409,15 @@ struct StrongestResult(u32);
 
 impl FromConnectors for StrongestResult {
     fn from_connectors(connectors: &[Connector]) -> Self {
-        StrongestResult(
-            connectors
-                .iter()
-                .map(|connector| connector.0 + connector.1)
-                .sum(),
-        )
+        StrongestResult(strength(connectors))
+    }
+
+    fn upper_bound(
+        connectors: &[Connector],
+        _remaining: usize,
+        remaining_strength: u32,
+    ) -> Option<Self> {
+        Some(StrongestResult(strength(connectors) + remaining_strength))
     }
 }
 
@@ -87,39 +448,78 @@ impl FromConnectors for LongestResult {
     fn from_connectors(connectors: &[Connector]) -> Self {
         LongestResult {
             length: connectors.len(),
-            strength: connectors
-                .iter()
-                .map(|connector| connector.0 + connector.1)
-                .sum(),
+            strength: strength(connectors),
         }
     }
+
+    fn upper_bound(
+        connectors: &[Connector],
+        remaining: usize,
+        remaining_strength: u32,
+    ) -> Option<Self> {
+        Some(LongestResult {
+            length: connectors.len() + remaining,
+            strength: strength(connectors) + remaining_strength,
+        })
+    }
+}
+
+// Bridges reaching at least `STRENGTH` rank above those that don't, and the
+// fewer connectors they use the better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct FewestReaching<const STRENGTH: u32>(Option<Reverse<usize>>);
+
+impl<const STRENGTH: u32> FromConnectors for FewestReaching<STRENGTH> {
+    fn from_connectors(connectors: &[Connector]) -> Self {
+        if strength(connectors) >= STRENGTH {
+            FewestReaching(Some(Reverse(connectors.len())))
+        } else {
+            FewestReaching(None)
+        }
+    }
+
+    fn upper_bound(
+        connectors: &[Connector],
+        _remaining: usize,
+        remaining_strength: u32,
+    ) -> Option<Self> {
+        let strength = strength(connectors);
+        Some(if strength >= STRENGTH {
+            FewestReaching(Some(Reverse(connectors.len())))
+        } else if strength + remaining_strength >= STRENGTH {
+            FewestReaching(Some(Reverse(connectors.len() + 1)))
+        } else {
+            FewestReaching(None)
+        })
+    }
 }
 
-fn strongest_bridge(connectors: &[Connector]) -> u32 {
-    find_best::<StrongestResult>(
-        0,
-        &mut connectors.iter().cloned().collect(),
-        &mut Vec::new(),
-    ).0
+// The strength no bridge can exceed, by the bounds the search prunes with.
+fn strength_bound(connectors: &[Connector]) -> Result<u32, Error> {
+    let graph = Graph::new(connectors)?;
+    let mut search = Search::<StrongestResult>::new(&graph, 0, false);
+    Ok(search.remaining(graph.start, 0).1)
 }
 
-fn longest_bridge(connectors: &[Connector]) -> u32 {
-    find_best::<LongestResult>(
-        0,
-        &mut connectors.iter().cloned().collect(),
-        &mut Vec::new(),
-    ).strength
+fn strongest_bridge(connectors: &[Connector]) -> Result<u32, Error> {
+    best_bridge::<StrongestResult>(connectors).map(|(result, _)| result.0)
 }
 
+fn longest_bridge(connectors: &[Connector]) -> Result<u32, Error> {
+    best_bridge::<LongestResult>(connectors).map(|(result, _)| result.strength)
+}
+
+// Duplicate connectors are kept, as each can be used once. `Report` lists
+// them.
 fn parse_connectors(s: &str) -> Result<Vec<Connector>, Error> {
     s.split('\n')
         .map(|line| {
-            let ports = line.split('/')
+            let ports = line
+                .split('/')
                 .map(str::parse::<u32>)
-                .take(2)
                 .collect::<Result<Vec<_>, _>>()?;
             if ports.len() != 2 {
-                bail!("Invalid number of ports");
+                bail!("Invalid number of ports in {}", line);
             }
             Ok(Connector(ports[0], ports[1]))
         })
@@ -128,31 +528,269 @@ fn parse_connectors(s: &str) -> Result<Vec<Connector>, Error> {
 
 fn main() {
     let input = include_str!("input.txt").trim();
-    let connectors = parse_connectors(&input).expect("parse");
+    let connectors = parse_connectors(input).expect("parse");
+    println!("{}", Report::new(&connectors));
+
+    // `--dot FILE` writes the connectors out as a graph.
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dot" {
+            let path = args.next().expect("dot file");
+            let mut file = BufWriter::new(File::create(path).expect("create"));
+            write_dot(&mut file, &connectors).expect("write");
+        }
+    }
 
-    let result = strongest_bridge(&connectors);
+    let result = strongest_bridge(&connectors).expect("search");
     println!("Result 1: {}", result);
 
-    let result = longest_bridge(&connectors);
+    let result = longest_bridge(&connectors).expect("search");
     println!("Result 2: {}", result);
+
+    let strongest = best_bridges::<StrongestResult>(&connectors).expect("search");
+    println!(
+        "{} bridges tie for strongest, first: {}",
+        strongest.len(),
+        strongest[0].1
+    );
+    let longest = top_bridges::<LongestResult>(&connectors, 3).expect("search");
+    println!("Longest bridges:");
+    for (result, bridge) in &longest {
+        println!(
+            "  {} long, {} strong: {}",
+            result.length, result.strength, bridge
+        );
+    }
+    let (result, bridge) = best_bridge::<FewestReaching<1000>>(&connectors).expect("search");
+    match result.0 {
+        Some(Reverse(length)) => println!(
+            "Fewest connectors reaching 1000: {} ({} strong): {}",
+            length,
+            bridge.strength(),
+            bridge
+        ),
+        None => println!("No bridge reaches 1000"),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Repeatable random connectors, from a linear congruential generator.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: u32) -> u32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % u64::from(n)) as u32
+        }
+
+        // `count` connectors with ports below `ports`.
+        fn connectors(&mut self, count: usize, ports: u32) -> Vec<Connector> {
+            (0..count)
+                .map(|_| Connector(self.below(ports), self.below(ports)))
+                .collect()
+        }
+    }
+
     #[test]
     fn find_strongest_test() {
         let input = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10";
-        let connectors = parse_connectors(&input).expect("parse");
+        let connectors = parse_connectors(input).expect("parse");
 
-        assert_eq!(strongest_bridge(&connectors), 31);
+        assert_eq!(strongest_bridge(&connectors).unwrap(), 31);
     }
 
     #[test]
     fn find_longest_test() {
         let input = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10";
-        let connectors = parse_connectors(&input).expect("parse");
-        assert_eq!(longest_bridge(&connectors), 19);
+        let connectors = parse_connectors(input).expect("parse");
+        assert_eq!(longest_bridge(&connectors).unwrap(), 19);
+    }
+
+    fn naive_bridges(
+        port: u32,
+        available: &mut Vec<Connector>,
+        bridge: &mut Bridge,
+    ) -> Vec<Bridge> {
+        let mut bridges = vec![bridge.clone()];
+        for i in 0..available.len() {
+            if let Some(next_port) = available[i].connects(port) {
+                let connector = available.remove(i);
+                bridge.links.push(Link { connector, port });
+                bridges.extend(naive_bridges(next_port, available, bridge));
+                available.insert(i, bridge.links.pop().unwrap().connector);
+            }
+        }
+        bridges
+    }
+
+    // Every bridge, ranked best first and by connectors after that.
+    fn naive_ranking<T: FromConnectors + Ord>(connectors: &[Connector]) -> Vec<(T, Bridge)> {
+        let mut ranking = naive_bridges(0, &mut connectors.to_vec(), &mut Bridge::default())
+            .into_iter()
+            .map(|bridge| (T::from_connectors(&bridge.connectors()), bridge))
+            .collect::<Vec<_>>();
+        ranking.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        ranking
+    }
+
+    fn naive_best<T: FromConnectors + Ord>(connectors: &[Connector]) -> T {
+        naive_ranking(connectors).remove(0).0
+    }
+
+    #[test]
+    fn find_best_test() {
+        let mut random = Random(12345);
+        for _ in 0..50 {
+            let connectors = random.connectors(12, 6);
+            assert_eq!(
+                best_bridge::<StrongestResult>(&connectors).unwrap().0,
+                naive_best(&connectors)
+            );
+            assert_eq!(
+                best_bridge::<LongestResult>(&connectors).unwrap().0,
+                naive_best(&connectors)
+            );
+        }
+
+        let connectors = (0..129).map(|i| Connector(i, i + 1)).collect::<Vec<_>>();
+        assert!(strongest_bridge(&connectors).is_err());
+    }
+
+    #[test]
+    fn bridge_test() {
+        let input = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10";
+        let connectors = parse_connectors(input).expect("parse");
+
+        let (result, bridge) = best_bridge::<StrongestResult>(&connectors).unwrap();
+        assert_eq!(result, StrongestResult(31));
+        assert_eq!(bridge.to_string(), "0/1--10/1--9/10");
+        assert_eq!(
+            bridge
+                .links
+                .iter()
+                .map(|link| link.port)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 10]
+        );
+        assert_eq!(bridge.strength(), 31);
+
+        let longest = best_bridges::<LongestResult>(&connectors).unwrap();
+        assert_eq!(
+            longest
+                .iter()
+                .map(|(_, bridge)| bridge.to_string())
+                .collect::<Vec<_>>(),
+            vec!["0/2--2/2--2/3--3/5"]
+        );
+
+        let top = top_bridges::<StrongestResult>(&connectors, 3).unwrap();
+        assert_eq!(
+            top.iter()
+                .map(|(_, bridge)| bridge.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "0/1--10/1--9/10",
+                "0/2--2/2--2/3--3/5",
+                "0/2--2/2--2/3--3/4"
+            ]
+        );
+
+        let (result, bridge) = best_bridge::<FewestReaching<20>>(&connectors).unwrap();
+        assert_eq!(result, FewestReaching(Some(Reverse(3))));
+        assert_eq!(bridge.to_string(), "0/1--10/1--9/10");
+        let (result, _) = best_bridge::<FewestReaching<100>>(&connectors).unwrap();
+        assert_eq!(result, FewestReaching(None));
+    }
+
+    #[test]
+    fn rank_test() {
+        let mut random = Random(54321);
+        for _ in 0..30 {
+            let connectors = random.connectors(9, 5);
+
+            let naive = naive_ranking::<StrongestResult>(&connectors);
+            let ties = naive
+                .iter()
+                .filter(|(score, _)| *score == naive[0].0)
+                .count();
+            assert_eq!(
+                best_bridges::<StrongestResult>(&connectors).unwrap(),
+                &naive[..ties]
+            );
+            assert_eq!(
+                top_bridges::<StrongestResult>(&connectors, 5).unwrap(),
+                &naive[..naive.len().min(5)]
+            );
+
+            let naive = naive_ranking::<LongestResult>(&connectors);
+            assert_eq!(
+                top_bridges::<LongestResult>(&connectors, 4).unwrap(),
+                &naive[..naive.len().min(4)]
+            );
+
+            let naive = naive_ranking::<FewestReaching<15>>(&connectors);
+            let (result, bridge) = best_bridge::<FewestReaching<15>>(&connectors).unwrap();
+            assert_eq!(result, naive[0].0);
+            assert_eq!(
+                FewestReaching::<15>::from_connectors(&bridge.connectors()),
+                result
+            );
+        }
+        assert!(top_bridges::<StrongestResult>(&[Connector(0, 1)], 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn connects_test() {
+        assert_eq!(Connector(3, 5).connects(3), Some(5));
+        assert_eq!(Connector(3, 5).connects(5), Some(3));
+        assert_eq!(Connector(3, 5).connects(4), None);
+        assert_eq!(Connector(4, 4).connects(4), Some(4));
+        assert_eq!(Connector(4, 4).connects(3), None);
+        assert_eq!(Connector(5, 3).normalized(), Connector(3, 5));
+    }
+
+    #[test]
+    fn parse_errors_test() {
+        assert!(parse_connectors("0/1\n1/2/3").is_err());
+        assert!(parse_connectors("0/1\n1").is_err());
+        assert!(parse_connectors("0/x").is_err());
+        assert_eq!(
+            parse_connectors("0/1\n1/0").unwrap(),
+            vec![Connector(0, 1), Connector(1, 0)]
+        );
+    }
+
+    #[test]
+    fn report_test() {
+        let input = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10\n7/8\n5/3";
+        let connectors = parse_connectors(input).expect("parse");
+        let report = Report::new(&connectors);
+        assert_eq!(report.connectors, 10);
+        assert_eq!(report.ports, 10);
+        // Ports 4, 9, 7 and 8 end one connector and 0, 1, 5 and 10 two. Port 3
+        // ends four, and port 2 two plus two for its loop.
+        assert_eq!(
+            report.degrees.into_iter().collect::<Vec<_>>(),
+            vec![(1, 4), (2, 4), (4, 2)]
+        );
+        assert_eq!(report.unreachable, vec![Connector(7, 8)]);
+        assert_eq!(report.duplicates, vec![(Connector(3, 5), 2)]);
+        assert!(report.strength_bound >= strongest_bridge(&connectors).unwrap());
+        assert!(report.strength_bound <= strength(&connectors) - 15);
+
+        let mut dot = Vec::new();
+        write_dot(&mut dot, &connectors[..2]).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "graph connectors {\n  0 [shape=doublecircle];\n  0 -- 2 [label=\"0/2\"];\n  2 -- 2 [label=\"2/2\"];\n}\n"
+        );
     }
 }
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use super::{strength, strength_bound, Connector};
+
+// What a set of connectors looks like as a multigraph on ports, where every
+// connector is an edge and an `a/a` one a loop adding two to the degree of `a`.
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub connectors: usize,
+    pub ports: usize,
+    // How many ports have each degree.
+    pub degrees: BTreeMap<usize, usize>,
+    pub unreachable: Vec<Connector>,
+    // Connectors given more than once, either way round, and how often.
+    pub duplicates: Vec<(Connector, usize)>,
+    pub strength_bound: u32,
+}
+
+impl Report {
+    pub fn new(connectors: &[Connector]) -> Report {
+        let mut degree = BTreeMap::new();
+        let mut counts = BTreeMap::new();
+        for connector in connectors {
+            *degree.entry(connector.0).or_insert(0) += 1;
+            *degree.entry(connector.1).or_insert(0) += 1;
+            *counts.entry(connector.normalized()).or_insert(0) += 1;
+        }
+        let mut degrees = BTreeMap::new();
+        for &d in degree.values() {
+            *degrees.entry(d).or_insert(0) += 1;
+        }
+
+        let reached = reachable(connectors);
+        let unreachable = connectors
+            .iter()
+            .filter(|connector| !reached.contains(&connector.0))
+            .cloned()
+            .collect::<Vec<_>>();
+        let reachable_strength = strength(connectors) - strength(&unreachable);
+
+        Report {
+            connectors: connectors.len(),
+            ports: degree.len(),
+            degrees,
+            unreachable,
+            duplicates: counts.into_iter().filter(|&(_, n)| n > 1).collect(),
+            strength_bound: strength_bound(connectors).unwrap_or(reachable_strength),
+        }
+    }
+}
+
+// The ports some bridge can reach.
+fn reachable(connectors: &[Connector]) -> BTreeSet<u32> {
+    let mut reached = BTreeSet::new();
+    let mut stack = vec![0];
+    while let Some(port) = stack.pop() {
+        if reached.insert(port) {
+            stack.extend(connectors.iter().filter_map(|c| c.connects(port)));
+        }
+    }
+    reached
+}
+
+fn join(connectors: &[Connector]) -> String {
+    connectors
+        .iter()
+        .map(|connector| format!("{}/{}", connector.0, connector.1))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} connectors on {} ports", self.connectors, self.ports)?;
+        writeln!(f, "Ports by degree:")?;
+        for (degree, ports) in &self.degrees {
+            writeln!(f, "  {:>3}: {}", degree, ports)?;
+        }
+        if self.unreachable.is_empty() {
+            writeln!(f, "All connectors are reachable from port 0")?;
+        } else {
+            writeln!(f, "Unreachable from port 0: {}", join(&self.unreachable))?;
+        }
+        if self.duplicates.is_empty() {
+            writeln!(f, "No duplicate connectors")?;
+        } else {
+            let duplicates = self
+                .duplicates
+                .iter()
+                .map(|(connector, n)| format!("{}/{} x{}", connector.0, connector.1, n))
+                .collect::<Vec<_>>();
+            writeln!(f, "Duplicates: {}", duplicates.join(", "))?;
+        }
+        write!(f, "No bridge is stronger than {}", self.strength_bound)
+    }
+}
+
+// Writes the connectors as an undirected DOT multigraph with a node per port
+// and an edge per connector, port 0 highlighted.
+pub fn write_dot<W: Write>(writer: &mut W, connectors: &[Connector]) -> io::Result<()> {
+    writeln!(writer, "graph connectors {{")?;
+    writeln!(writer, "  0 [shape=doublecircle];")?;
+    for connector in connectors {
+        writeln!(
+            writer,
+            "  {} -- {} [label=\"{}/{}\"];",
+            connector.0, connector.1, connector.0, connector.1
+        )?;
+    }
+    writeln!(writer, "}}")
+}
@@ -0,0 +1,248 @@
+use failure::Error;
+use std::collections::HashMap;
+
+use super::{Program, States, HALT};
+
+// What running a state on a block does until the head leaves it, from
+// whichever end it started at.
+#[derive(Clone, Copy, Debug)]
+struct BlockTransition {
+    block: u64,
+    state: usize,
+    right: bool,
+    steps: u64,
+}
+
+// A machine working on blocks of `size` cells, cell `i` of a block being bit
+// `i`. The tape is kept as runs of identical blocks on either side of the
+// head's block, nearest last, and blank past them. Transitions from a block
+// edge are remembered, and a state that leaves a run of blocks the way it
+// came in, unchanged, crosses the whole run at once.
+pub struct MacroMachine {
+    program: Program,
+    size: usize,
+    transitions: HashMap<(usize, u64, bool), Option<BlockTransition>>,
+    left: Vec<(u64, u64)>,
+    right: Vec<(u64, u64)>,
+    block: u64,
+    position: usize,
+    state: usize,
+    ones: u64,
+    pub steps: u64,
+}
+
+fn push(runs: &mut Vec<(u64, u64)>, block: u64, count: u64) {
+    match runs.last_mut() {
+        Some(run) if run.0 == block => run.1 += count,
+        _ => runs.push((block, count)),
+    }
+}
+
+impl MacroMachine {
+    pub fn new(states: &States, starting_state: char, size: usize) -> Result<MacroMachine, Error> {
+        if size == 0 || size > 32 {
+            bail!("Blocks must be 1 to 32 cells");
+        }
+        let program = Program::compile(states)?;
+        Ok(MacroMachine {
+            state: program.state(starting_state)?,
+            program,
+            size,
+            transitions: HashMap::new(),
+            left: Vec::new(),
+            right: Vec::new(),
+            block: 0,
+            position: 0,
+            ones: 0,
+            steps: 0,
+        })
+    }
+
+    pub fn checksum(&self) -> usize {
+        self.ones as usize
+    }
+
+    pub fn halted(&self) -> bool {
+        self.state == HALT
+    }
+
+    // Runs one step within the current block, returning whether the head
+    // left it and which way.
+    fn step(&mut self) -> Option<bool> {
+        let bit = 1 << self.position;
+        let value = self.block & bit != 0;
+        let transition = match self.program.table[2 * self.state + value as usize] {
+            Some(transition) => transition,
+            None => {
+                self.state = HALT;
+                return None;
+            }
+        };
+        if transition.write != value {
+            self.block ^= bit;
+            if transition.write {
+                self.ones += 1;
+            } else {
+                self.ones -= 1;
+            }
+        }
+        self.state = transition.next;
+        self.steps += 1;
+        if transition.right {
+            if self.position + 1 == self.size {
+                return Some(true);
+            }
+            self.position += 1;
+        } else {
+            if self.position == 0 {
+                return Some(false);
+            }
+            self.position -= 1;
+        }
+        None
+    }
+
+    // Runs `state` on `block` from its left or right end until the head
+    // leaves, or `None` if it never does as it halts or loops. Loops are found
+    // by Brent's algorithm, comparing the configuration with one saved at
+    // doubling intervals.
+    fn block_transition(&self, state: usize, block: u64, right: bool) -> Option<BlockTransition> {
+        let (mut state, mut block) = (state, block);
+        let mut position = if right { self.size - 1 } else { 0 };
+        let mut saved = (state, block, position);
+        let (mut power, mut length) = (1u64, 0u64);
+        for steps in 1.. {
+            let bit = 1 << position;
+            if state == HALT {
+                return None;
+            }
+            let transition = self.program.table[2 * state + (block & bit != 0) as usize]?;
+            if transition.write {
+                block |= bit;
+            } else {
+                block &= !bit;
+            }
+            state = transition.next;
+            let leaves = if transition.right {
+                position + 1 == self.size
+            } else {
+                position == 0
+            };
+            if leaves {
+                return Some(BlockTransition {
+                    block,
+                    state,
+                    right: transition.right,
+                    steps,
+                });
+            }
+            if transition.right {
+                position += 1;
+            } else {
+                position -= 1;
+            }
+
+            if (state, block, position) == saved {
+                return None;
+            }
+            length += 1;
+            if length == power {
+                saved = (state, block, position);
+                power *= 2;
+                length = 0;
+            }
+        }
+        None
+    }
+
+    fn transition(&mut self, state: usize, block: u64, right: bool) -> Option<BlockTransition> {
+        if let Some(&transition) = self.transitions.get(&(state, block, right)) {
+            return transition;
+        }
+        let transition = self.block_transition(state, block, right);
+        self.transitions.insert((state, block, right), transition);
+        transition
+    }
+
+    // The runs ahead of the head when it moves right, or else left.
+    fn runs(&mut self, right: bool) -> &mut Vec<(u64, u64)> {
+        if right {
+            &mut self.right
+        } else {
+            &mut self.left
+        }
+    }
+
+    // Moves the head out of the current block, crossing any runs ahead the
+    // state would cross unchanged, as far as `remaining` steps allow.
+    fn leave(&mut self, right: bool, remaining: &mut u64) {
+        let block = self.block;
+        push(self.runs(!right), block, 1);
+        while let Some(&(block, count)) = self.runs(right).last() {
+            let t = match self.transition(self.state, block, !right) {
+                Some(t) if t.right == right && t.state == self.state => t,
+                _ => break,
+            };
+            let crossed = count.min(*remaining / t.steps);
+            if crossed == 0 {
+                break;
+            }
+            push(self.runs(!right), t.block, crossed);
+            self.ones = self.ones + u64::from(t.block.count_ones()) * crossed
+                - u64::from(block.count_ones()) * crossed;
+            self.steps += t.steps * crossed;
+            *remaining -= t.steps * crossed;
+            let ahead = self.runs(right);
+            if crossed == count {
+                ahead.pop();
+            } else {
+                ahead.last_mut().unwrap().1 -= crossed;
+            }
+        }
+
+        let ahead = self.runs(right);
+        let block = match ahead.pop() {
+            Some((block, count)) => {
+                if count > 1 {
+                    ahead.push((block, count - 1));
+                }
+                block
+            }
+            None => 0,
+        };
+        self.block = block;
+        self.position = if right { 0 } else { self.size - 1 };
+    }
+
+    // Runs for `steps` steps, or until the machine halts.
+    pub fn run(&mut self, steps: u64) {
+        let mut remaining = steps;
+        while remaining > 0 && !self.halted() {
+            let edge = if self.position == 0 {
+                Some(false)
+            } else if self.position + 1 == self.size {
+                Some(true)
+            } else {
+                None
+            };
+            let transition = edge.and_then(|edge| self.transition(self.state, self.block, edge));
+            match transition {
+                Some(t) if t.steps <= remaining => {
+                    self.ones = self.ones + u64::from(t.block.count_ones())
+                        - u64::from(self.block.count_ones());
+                    self.block = t.block;
+                    self.state = t.state;
+                    self.steps += t.steps;
+                    remaining -= t.steps;
+                    self.leave(t.right, &mut remaining);
+                }
+                _ => {
+                    remaining -= 1;
+                    if let Some(right) = self.step() {
+                        self.leave(right, &mut remaining);
+                    }
+                }
+            }
+        }
+    }
+}
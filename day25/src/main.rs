@@ -2,11 +2,18 @@
 extern crate failure;
 extern crate regex;
 
+mod macro_machine;
+mod notation;
+mod tape;
+
 use failure::Error;
+use macro_machine::MacroMachine;
+use notation::{parse_json, parse_standard, parse_yaml, to_dot, to_json, to_standard, to_yaml};
 use regex::Regex;
 use std::collections::HashMap;
-use std::collections::VecDeque;
+use std::env;
 use std::str::FromStr;
+use tape::Tape;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Value {
@@ -21,7 +28,7 @@ impl FromStr for Value {
         match s {
             "0" => Ok(Value::Zero),
             "1" => Ok(Value::One),
-            _ => Err(format_err!("Invalid"))
+            _ => Err(format_err!("Invalid")),
         }
     }
 }
@@ -39,22 +46,27 @@ impl FromStr for Movement {
         match s {
             "left" => Ok(Movement::Left),
             "right" => Ok(Movement::Right),
-            _ => Err(format_err!("Invalid"))
+            _ => Err(format_err!("Invalid")),
         }
     }
 }
 
+// `halts` is set when `next_state` is only a name for halting, as in the
+// standard format, rather than a state with a blueprint.
 #[derive(Debug)]
 struct SubState {
     write: Value,
     movement: Movement,
     next_state: char,
+    halts: bool,
 }
 
+// What to do on reading each value. The machine halts on reading a value
+// without one, or after a transition that halts.
 #[derive(Debug)]
 struct State {
-    zero: SubState,
-    one: SubState,
+    zero: Option<SubState>,
+    one: Option<SubState>,
 }
 
 #[derive(Debug)]
@@ -63,7 +75,18 @@ struct States(HashMap<char, State>);
 impl FromStr for States {
     type Err = Error;
 
+    // Either the puzzle's blueprints, or a machine in the standard format or
+    // as a JSON or YAML transition table.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.trim().contains(char::is_whitespace) {
+            return parse_standard(s.trim());
+        }
+        if s.trim_start().starts_with('{') {
+            return parse_json(s);
+        }
+        if s.trim_start().starts_with('"') {
+            return parse_yaml(s);
+        }
         let state_re = Regex::new(r"In state ([A-Z]):").unwrap();
         let sub_state_re = Regex::new(r"If the current value is (0|1):").unwrap();
         let write_re = Regex::new(r"- Write the value (0|1).").unwrap();
@@ -93,6 +116,7 @@ impl FromStr for States {
                         .chars()
                         .nth(0)
                         .unwrap(),
+                    halts: false,
                 };
                 ensure!(
                     &sub_state_re.captures(iter.next().unwrap()).unwrap()[1] == "1",
@@ -113,12 +137,13 @@ impl FromStr for States {
                         .chars()
                         .nth(0)
                         .unwrap(),
+                    halts: false,
                 };
                 states.insert(
                     caps[1].chars().nth(0).unwrap(),
                     State {
-                        zero: zero,
-                        one: one,
+                        zero: Some(zero),
+                        one: Some(one),
                     },
                 );
             }
@@ -127,60 +152,527 @@ impl FromStr for States {
     }
 }
 
+// The state number of a halted machine.
+const HALT: usize = usize::MAX;
+
+// One transition of a compiled program, to a state by its number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    write: bool,
+    right: bool,
+    next: usize,
+}
+
+// The blueprints with states numbered in alphabetical order and the
+// transition for state `s` reading `v` at `table[2 * s + v]`, if any.
+#[derive(Debug)]
+struct Program {
+    names: Vec<char>,
+    table: Vec<Option<Transition>>,
+}
+
+impl Program {
+    fn compile(states: &States) -> Result<Program, Error> {
+        let mut names = states.0.keys().cloned().collect::<Vec<_>>();
+        names.sort_unstable();
+        let mut program = Program {
+            names,
+            table: Vec::new(),
+        };
+        program.table = program
+            .names
+            .iter()
+            .flat_map(|name| {
+                let state = &states.0[name];
+                vec![&state.zero, &state.one]
+            })
+            .map(|sub_state| {
+                sub_state
+                    .as_ref()
+                    .map(|s| program.transition(s))
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(program)
+    }
+
+    fn state(&self, name: char) -> Result<usize, Error> {
+        self.names
+            .binary_search(&name)
+            .map_err(|_| format_err!("Unknown state {}", name))
+    }
+
+    fn transition(&self, sub_state: &SubState) -> Result<Transition, Error> {
+        Ok(Transition {
+            write: sub_state.write == Value::One,
+            right: match sub_state.movement {
+                Movement::Left => false,
+                Movement::Right => true,
+            },
+            next: if sub_state.halts {
+                HALT
+            } else {
+                self.state(sub_state.next_state)?
+            },
+        })
+    }
+}
+
 struct TuringMachine {
-    states: States,
-    tape: VecDeque<Value>,
-    current_state: char,
-    current_index: usize,
+    program: Program,
+    tape: Tape,
+    state: usize,
+    head: usize,
+    steps: u64,
 }
 
 impl TuringMachine {
-    fn new(states: States, starting_state: char) -> TuringMachine {
-        TuringMachine {
-            states: states,
-            tape: [Value::Zero].into_iter().cloned().collect(),
-            current_state: starting_state,
-            current_index: 0,
-        }
+    fn new(states: &States, starting_state: char) -> Result<TuringMachine, Error> {
+        let program = Program::compile(states)?;
+        let tape = Tape::new();
+        Ok(TuringMachine {
+            state: program.state(starting_state)?,
+            head: tape.len() / 2,
+            program,
+            tape,
+            steps: 0,
+        })
     }
+
+    fn halted(&self) -> bool {
+        self.state == HALT
+    }
+
     fn step(&mut self) {
-        let state = match self.tape[self.current_index] {
-            Value::Zero => {
-                &self.states.0[&self.current_state].zero
-            }
-            Value::One => {
-                &self.states.0[&self.current_state].one
+        if self.halted() {
+            return;
+        }
+        let value = self.tape.get(self.head);
+        let transition = match self.program.table[2 * self.state + value as usize] {
+            Some(transition) => transition,
+            None => {
+                self.state = HALT;
+                return;
             }
         };
-        self.tape[self.current_index] = state.write;
-        match state.movement {
-            Movement::Left => {
-                if self.current_index == 0 {
-                    self.tape.push_front(Value::Zero);
-                } else {
-                    self.current_index -= 1;
-                }
+        self.tape.set(self.head, transition.write);
+        if transition.right {
+            self.head += 1;
+            if self.head == self.tape.len() {
+                self.tape.grow_right();
             }
-            Movement::Right => {
-                self.current_index += 1;
-                if self.current_index == self.tape.len() {
-                    self.tape.push_back(Value::Zero);
-                }
+        } else {
+            if self.head == 0 {
+                self.head += self.tape.grow_left();
             }
-        };
-        self.current_state = state.next_state;
+            self.head -= 1;
+        }
+        self.state = transition.next;
+        self.steps += 1;
+    }
+
+    // Runs for `steps` steps, or until the machine halts.
+    fn run(&mut self, steps: u64) {
+        for _ in 0..steps {
+            if self.halted() {
+                break;
+            }
+            self.step();
+        }
     }
 
     fn checksum(&self) -> usize {
-        self.tape.iter().filter(|&val| *val == Value::One).count()
+        self.tape.ones()
     }
 }
 
 fn main() {
     let input = include_str!("input.txt");
-    let states: States = input.parse().expect("parse");
-    let mut machine = TuringMachine::new(states, 'A');
-    (0..12_586_542).for_each(|_| machine.step());
+    let mut states: States = input.parse().expect("parse");
+    let mut steps = 12_586_542;
+
+    // `--macro K` runs the blueprints as a macro machine on blocks of K cells.
+    // `--machine M` runs another machine instead, such as one in the standard
+    // format or a JSON or YAML table, for `--steps N` or until it halts. `--export FORMAT` prints the
+    // machine as `standard`, `json`, `yaml` or `dot`, and only runs it too
+    // with `--steps`.
+    let mut args = env::args().skip(1);
+    let mut block_size = None;
+    let mut export = None;
+    let mut steps_given = false;
+    while let Some(arg) = args.next() {
+        if arg == "--macro" {
+            block_size = args
+                .next()
+                .map(|arg| arg.parse::<usize>().expect("block size"));
+        } else if arg == "--machine" {
+            states = args.next().expect("machine").parse().expect("parse");
+        } else if arg == "--steps" {
+            steps = args.next().expect("steps").parse().expect("steps");
+            steps_given = true;
+        } else if arg == "--export" {
+            export = args.next();
+        }
+    }
+
+    match export.as_deref() {
+        Some("standard") => println!("{}", to_standard(&states).expect("standard")),
+        Some("json") => print!("{}", to_json(&states)),
+        Some("yaml") => print!("{}", to_yaml(&states)),
+        Some("dot") => print!("{}", to_dot(&states, 'A')),
+        Some(format) => panic!("Unknown format {}", format),
+        None => (),
+    }
+    if export.is_some() && !steps_given {
+        return;
+    }
+
+    let (checksum, halted) = match block_size {
+        Some(size) => {
+            let mut machine = MacroMachine::new(&states, 'A', size).expect("compile");
+            machine.run(steps);
+            (
+                machine.checksum(),
+                Some(machine.steps).filter(|_| machine.halted()),
+            )
+        }
+        None => {
+            let mut machine = TuringMachine::new(&states, 'A').expect("compile");
+            machine.run(steps);
+            (
+                machine.checksum(),
+                Some(machine.steps).filter(|_| machine.halted()),
+            )
+        }
+    };
+    println!("Result 1: {}", checksum);
+    if let Some(steps) = halted {
+        println!("Halted after {} steps", steps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Begin in state A.
+Perform a diagnostic checksum after 6 steps.
+
+In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 0.
+    - Move one slot to the left.
+    - Continue with state B.
 
-    println!("Result 1: {}", machine.checksum());
+In state B:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+";
+
+    #[test]
+    fn example_test() {
+        let states: States = EXAMPLE.parse().expect("parse");
+        let mut machine = TuringMachine::new(&states, 'A').unwrap();
+        machine.run(6);
+        assert_eq!(machine.checksum(), 3);
+        assert!(TuringMachine::new(&states, 'C').is_err());
+
+        let typo: States = EXAMPLE
+            .replace("Continue with state A", "Continue with state X")
+            .parse()
+            .unwrap();
+        assert!(TuringMachine::new(&typo, 'A').is_err());
+        assert!(MacroMachine::new(&typo, 'A', 4).is_err());
+    }
+
+    // Runs the blueprints on a tape of cells by position, checking the count
+    // of ones after every step.
+    fn check_against_naive(states: &States, steps: u64) {
+        let mut machine = TuringMachine::new(states, 'A').unwrap();
+        let mut tape = HashMap::new();
+        let (mut position, mut state, mut ones) = (0i64, 'A', 0);
+        for _ in 0..steps {
+            let value = tape.get(&position).cloned().unwrap_or(Value::Zero);
+            let sub_state = match value {
+                Value::Zero => states.0[&state].zero.as_ref().unwrap(),
+                Value::One => states.0[&state].one.as_ref().unwrap(),
+            };
+            if value != sub_state.write {
+                ones = ones + (sub_state.write == Value::One) as usize
+                    - (value == Value::One) as usize;
+            }
+            tape.insert(position, sub_state.write);
+            position += match sub_state.movement {
+                Movement::Left => -1,
+                Movement::Right => 1,
+            };
+            state = sub_state.next_state;
+
+            machine.step();
+            assert_eq!(machine.checksum(), ones);
+        }
+    }
+
+    #[test]
+    fn growth_test() {
+        // Writes alternating ones and zeros heading one way, then the other.
+        let runner = |first: &str, second: &str| {
+            format!(
+                "In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the {0}.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 0.
+    - Move one slot to the {1}.
+    - Continue with state A.
+
+In state B:
+  If the current value is 0:
+    - Write the value 0.
+    - Move one slot to the {0}.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the {1}.
+    - Continue with state B.
+",
+                first, second
+            )
+            .parse::<States>()
+            .unwrap()
+        };
+        check_against_naive(&runner("left", "right"), 20_000);
+        check_against_naive(&runner("right", "left"), 20_000);
+        check_against_naive(&include_str!("input.txt").parse().unwrap(), 20_000);
+    }
+
+    // Repeatable random numbers, from a linear congruential generator.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % n
+        }
+    }
+
+    // Random blueprints with states A onwards, as prose.
+    fn random_states(random: &mut Random, count: u8) -> States {
+        let mut prose = String::new();
+        for state in 0..count {
+            prose.push_str(&format!("In state {}:\n", (b'A' + state) as char));
+            for value in 0..2 {
+                prose.push_str(&format!(
+                    "  If the current value is {}:\n    - Write the value {}.\n    - Move one slot to the {}.\n    - Continue with state {}.\n",
+                    value,
+                    random.below(2),
+                    if random.below(2) == 0 { "left" } else { "right" },
+                    (b'A' + random.below(u64::from(count)) as u8) as char
+                ));
+            }
+        }
+        prose.parse().unwrap()
+    }
+
+    #[test]
+    fn macro_machine_test() {
+        let mut random = Random(2017);
+        for _ in 0..100 {
+            let count = 2 + random.below(4) as u8;
+            let states = random_states(&mut random, count);
+            let size = 1 + random.below(8) as usize;
+            let mut machine = TuringMachine::new(&states, 'A').unwrap();
+            let mut macro_machine = MacroMachine::new(&states, 'A', size).unwrap();
+            for i in 0..50 {
+                let steps = random.below(if i % 10 == 9 { 20_000 } else { 500 });
+                machine.run(steps);
+                macro_machine.run(steps);
+                assert_eq!(macro_machine.checksum(), machine.checksum());
+                assert_eq!(macro_machine.steps, machine.steps);
+            }
+        }
+
+        let states: States = include_str!("input.txt").parse().unwrap();
+        let mut machine = MacroMachine::new(&states, 'A', 6).unwrap();
+        machine.run(12_586_542);
+        assert_eq!(machine.checksum(), 3732);
+        assert!(MacroMachine::new(&states, 'A', 33).is_err());
+    }
+
+    #[test]
+    fn sweep_test() {
+        // Sweeps back and forth over a run of ones, adding one at either end.
+        let sweep: States = "In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+
+In state B:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state B.
+"
+        .parse()
+        .unwrap();
+        let mut machine = TuringMachine::new(&sweep, 'A').unwrap();
+        let mut macro_machine = MacroMachine::new(&sweep, 'A', 8).unwrap();
+        machine.run(1_000_000);
+        macro_machine.run(1_000_000);
+        assert_eq!(machine.checksum(), 1414);
+        assert_eq!(macro_machine.checksum(), 1414);
+        // Crossing the run a block at a time would take hours.
+        macro_machine.run(1_000_000_000_000 - 1_000_000);
+        assert_eq!(macro_machine.checksum(), 1_414_214);
+    }
+
+    #[test]
+    fn block_loop_test() {
+        // Bounces between two cells for ever, within any block of two or more.
+        let states: States = "0RB1RB_0LA1LA".parse().unwrap();
+        for &size in &[2, 24, 32] {
+            let mut machine = MacroMachine::new(&states, 'A', size).unwrap();
+            machine.run(10);
+            assert_eq!((machine.steps, machine.checksum()), (10, 0));
+            machine.run(1_000);
+            assert_eq!((machine.steps, machine.halted()), (1_010, false));
+        }
+    }
+
+    // The five state busy beaver champion, writing 4098 ones in 47176870
+    // steps before halting.
+    const BB5: &str = "1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA";
+
+    #[test]
+    fn standard_test() {
+        let states: States = BB5.parse().unwrap();
+        assert_eq!(to_standard(&states).unwrap(), BB5);
+        let mut machine = MacroMachine::new(&states, 'A', 6).unwrap();
+        machine.run(u64::MAX);
+        assert!(machine.halted());
+        assert_eq!(machine.steps, 47_176_870);
+        assert_eq!(machine.checksum(), 4098);
+
+        // Halting on an undefined transition takes no step.
+        let states: States = "1RB---_1LA1RA".parse().unwrap();
+        let mut machine = TuringMachine::new(&states, 'A').unwrap();
+        machine.run(100);
+        assert!(machine.halted());
+        assert_eq!((machine.steps, machine.checksum()), (2, 2));
+        let mut macro_machine = MacroMachine::new(&states, 'A', 4).unwrap();
+        macro_machine.run(100);
+        assert!(macro_machine.halted());
+        assert_eq!((macro_machine.steps, macro_machine.checksum()), (2, 2));
+
+        let puzzle: States = include_str!("input.txt").parse().unwrap();
+        let standard = to_standard(&puzzle).unwrap();
+        assert_eq!(to_standard(&standard.parse().unwrap()).unwrap(), standard);
+        let mut machine = TuringMachine::new(&standard.parse().unwrap(), 'A').unwrap();
+        machine.run(12_586_542);
+        assert_eq!(machine.checksum(), 3732);
+
+        assert!(parse_standard("1RB1LC_1RC").is_err());
+        assert!(parse_standard("1RB1XC").is_err());
+        assert!(parse_standard("2RB1LC").is_err());
+        assert!(parse_standard("1Rb1LC").is_err());
+        let mut gap = HashMap::new();
+        gap.insert('B', puzzle.0.into_iter().next().unwrap().1);
+        assert!(to_standard(&States(gap)).is_err());
+    }
+
+    #[test]
+    fn export_test() {
+        let states: States = "1RB---_0LA1RZ".parse().unwrap();
+        assert_eq!(
+            to_json(&states),
+            r#"{
+  "A": {
+    "0": {"write": 1, "move": "right", "next": "B"},
+    "1": null
+  },
+  "B": {
+    "0": {"write": 0, "move": "left", "next": "A"},
+    "1": {"write": 1, "move": "right", "next": "Z"}
+  }
+}
+"#
+        );
+        assert_eq!(
+            to_yaml(&states),
+            r#""A":
+  "0": {write: 1, move: right, next: "B"}
+  "1": null
+"B":
+  "0": {write: 0, move: left, next: "A"}
+  "1": {write: 1, move: right, next: "Z"}
+"#
+        );
+        assert_eq!(
+            to_dot(&states, 'A'),
+            r#"digraph machine {
+  rankdir=LR;
+  start [shape=point];
+  start -> A;
+  A -> B [label="0/1 R"];
+  B -> A [label="0/0 L"];
+  B -> Z [label="1/1 R"];
+  Z [shape=doublecircle];
+}
+"#
+        );
+
+        let puzzle: States = include_str!("input.txt").parse().unwrap();
+        for states in &[states, BB5.parse().unwrap(), puzzle] {
+            let json = to_json(states);
+            let imported = parse_json(&json).unwrap();
+            assert_eq!(to_json(&imported), json);
+            assert_eq!(
+                to_standard(&imported).unwrap(),
+                to_standard(states).unwrap()
+            );
+            let yaml = to_yaml(states);
+            let imported: States = yaml.parse().unwrap();
+            assert_eq!(to_yaml(&imported), yaml);
+            assert_eq!(
+                to_standard(&imported).unwrap(),
+                to_standard(states).unwrap()
+            );
+        }
+
+        // Transitions to states missing from a table halt.
+        let states = parse_json(&to_json(&"1RB1LA_1LA1RZ".parse().unwrap())).unwrap();
+        let mut machine = TuringMachine::new(&states, 'A').unwrap();
+        machine.run(100);
+        assert!(machine.halted());
+        assert_eq!((machine.steps, machine.checksum()), (5, 3));
+
+        assert!(parse_json("{\"A\": {\"0\": {\"write\": 2}}}").is_err());
+        assert!(parse_json("{\"A\": {\"2\": null}} x").is_err());
+        assert!(parse_yaml("\"A\":\n  \"0\": {write: 1, move: up, next: B}\n").is_err());
+        assert!(parse_yaml("  \"0\": null\n").is_err());
+    }
 }
@@ -0,0 +1,391 @@
+use failure::Error;
+use std::collections::{HashMap, HashSet};
+
+use super::{Movement, State, States, SubState, Value};
+
+fn digit(value: Value) -> char {
+    match value {
+        Value::Zero => '0',
+        Value::One => '1',
+    }
+}
+
+fn direction(movement: &Movement) -> &'static str {
+    match movement {
+        Movement::Left => "left",
+        Movement::Right => "right",
+    }
+}
+
+fn letter(movement: &Movement) -> char {
+    match movement {
+        Movement::Left => 'L',
+        Movement::Right => 'R',
+    }
+}
+
+// Transitions to states past the `count` given halt.
+fn parse_sub_state(s: &str, count: usize) -> Result<Option<SubState>, Error> {
+    if s == "---" {
+        return Ok(None);
+    }
+    let chars = s.chars().collect::<Vec<_>>();
+    if chars.len() != 3 || !chars[2].is_ascii_uppercase() {
+        bail!("Invalid transition {}", s);
+    }
+    Ok(Some(SubState {
+        write: chars[0].to_string().parse()?,
+        movement: match chars[1] {
+            'L' => Movement::Left,
+            'R' => Movement::Right,
+            _ => bail!("Invalid movement in {}", s),
+        },
+        next_state: chars[2],
+        halts: (chars[2] as u8 - b'A') as usize >= count,
+    }))
+}
+
+// Parses the busy beaver community's standard format, such as
+// `1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA`: states A onwards separated by `_`,
+// each as what to write, which way to move and which state is next on reading
+// 0 and then 1. `---` leaves a transition undefined, and one to a state not
+// listed, like `Z`, halts after it.
+pub fn parse_standard(s: &str) -> Result<States, Error> {
+    let mut states = HashMap::new();
+    let count = s.split('_').count();
+    for (i, state) in s.split('_').enumerate() {
+        if i >= 26 || state.len() != 6 || !state.is_ascii() {
+            bail!("Invalid state {}", state);
+        }
+        states.insert(
+            (b'A' + i as u8) as char,
+            State {
+                zero: parse_sub_state(&state[..3], count)?,
+                one: parse_sub_state(&state[3..], count)?,
+            },
+        );
+    }
+    Ok(States(states))
+}
+
+// The states by name, with each name's value transitions.
+fn sorted(states: &States) -> Vec<(char, [&Option<SubState>; 2])> {
+    let mut sorted = states
+        .0
+        .iter()
+        .map(|(&name, state)| (name, [&state.zero, &state.one]))
+        .collect::<Vec<_>>();
+    sorted.sort_by_key(|&(name, _)| name);
+    sorted
+}
+
+// Writes the states in the standard format, which needs them to be named A
+// onwards.
+pub fn to_standard(states: &States) -> Result<String, Error> {
+    let sorted = sorted(states);
+    let mut standard = Vec::new();
+    for (i, (name, sub_states)) in sorted.iter().enumerate() {
+        if *name != (b'A' + i as u8) as char {
+            bail!("States must be named A onwards, not {}", name);
+        }
+        standard.push(
+            sub_states
+                .iter()
+                .map(|sub_state| match sub_state {
+                    Some(s) => {
+                        format!("{}{}{}", digit(s.write), letter(&s.movement), s.next_state)
+                    }
+                    None => "---".to_string(),
+                })
+                .collect::<String>(),
+        );
+    }
+    Ok(standard.join("_"))
+}
+
+// The transition table as JSON, states mapping each value read to what the
+// machine does or `null` if it halts.
+pub fn to_json(states: &States) -> String {
+    let entries = sorted(states)
+        .into_iter()
+        .map(|(name, sub_states)| {
+            let values = sub_states
+                .iter()
+                .enumerate()
+                .map(|(value, sub_state)| match sub_state {
+                    Some(s) => format!(
+                        "    \"{}\": {{\"write\": {}, \"move\": \"{}\", \"next\": \"{}\"}}",
+                        value,
+                        digit(s.write),
+                        direction(&s.movement),
+                        s.next_state
+                    ),
+                    None => format!("    \"{}\": null", value),
+                })
+                .collect::<Vec<_>>();
+            format!("  \"{}\": {{\n{}\n  }}", name, values.join(",\n"))
+        })
+        .collect::<Vec<_>>();
+    format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+// The same table as `to_json`, as YAML. Names and values read are quoted so
+// that YAML 1.1 parsers don't take states like `Y` and `N` for booleans, or
+// the values for numbers.
+pub fn to_yaml(states: &States) -> String {
+    let mut yaml = String::new();
+    for (name, sub_states) in sorted(states) {
+        yaml.push_str(&format!("\"{}\":\n", name));
+        for (value, sub_state) in sub_states.iter().enumerate() {
+            yaml.push_str(&match sub_state {
+                Some(s) => format!(
+                    "  \"{}\": {{write: {}, move: {}, next: \"{}\"}}\n",
+                    value,
+                    digit(s.write),
+                    direction(&s.movement),
+                    s.next_state
+                ),
+                None => format!("  \"{}\": null\n", value),
+            });
+        }
+    }
+    yaml
+}
+
+// What a machine does on reading each value, as tables name it: what to
+// write, which way to move and which state is next.
+type Entry = (String, Option<(Value, Movement, char)>);
+
+fn state_name(s: &str) -> Result<char, Error> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(name), None) if name.is_ascii_uppercase() => Ok(name),
+        _ => bail!("Invalid state name {}", s),
+    }
+}
+
+// Builds the states from what each does on reading 0 and 1. As in the
+// standard format, a transition to a state not in the table halts after it.
+fn from_table(table: Vec<(char, Vec<Entry>)>) -> Result<States, Error> {
+    let names = table.iter().map(|&(name, _)| name).collect::<HashSet<_>>();
+    let mut states = HashMap::new();
+    for (name, entries) in table {
+        let mut sub_states = [None, None];
+        for (value, transition) in entries {
+            let i = match value.as_str() {
+                "0" => 0,
+                "1" => 1,
+                _ => bail!("Invalid value {} in state {}", value, name),
+            };
+            sub_states[i] = transition.map(|(write, movement, next_state)| SubState {
+                write,
+                movement,
+                next_state,
+                halts: !names.contains(&next_state),
+            });
+        }
+        let [zero, one] = sub_states;
+        if states.insert(name, State { zero, one }).is_some() {
+            bail!("State {} is given twice", name);
+        }
+    }
+    Ok(States(states))
+}
+
+// Just enough JSON for `to_json`'s tables: objects, strings without escapes,
+// numbers and `null`.
+#[derive(Debug)]
+enum Json {
+    Object(Vec<(String, Json)>),
+    String(String),
+    Number(String),
+    Null,
+}
+
+impl Json {
+    fn into_object(self) -> Result<Vec<(String, Json)>, Error> {
+        match self {
+            Json::Object(entries) => Ok(entries),
+            other => bail!("Expected an object, not {:?}", other),
+        }
+    }
+
+    fn into_text(self) -> Result<String, Error> {
+        match self {
+            Json::String(text) | Json::Number(text) => Ok(text),
+            other => bail!("Expected a string or number, not {:?}", other),
+        }
+    }
+}
+
+// Parses the JSON value at the start of `s`, leaving `s` after it.
+fn parse_json_value(s: &mut &str) -> Result<Json, Error> {
+    *s = s.trim_start();
+    if let Some(rest) = s.strip_prefix("null") {
+        *s = rest;
+        return Ok(Json::Null);
+    }
+    if let Some(rest) = s.strip_prefix('"') {
+        let end = rest
+            .find('"')
+            .ok_or_else(|| format_err!("Unterminated string"))?;
+        *s = &rest[end + 1..];
+        return Ok(Json::String(rest[..end].to_string()));
+    }
+    if let Some(rest) = s.strip_prefix('{') {
+        let mut entries = Vec::new();
+        *s = rest.trim_start();
+        if let Some(rest) = s.strip_prefix('}') {
+            *s = rest;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            let key = parse_json_value(s)?.into_text()?;
+            *s = s
+                .trim_start()
+                .strip_prefix(':')
+                .ok_or_else(|| format_err!("Expected : after {}", key))?;
+            entries.push((key, parse_json_value(s)?));
+            *s = s.trim_start();
+            if let Some(rest) = s.strip_prefix(',') {
+                *s = rest;
+            } else if let Some(rest) = s.strip_prefix('}') {
+                *s = rest;
+                return Ok(Json::Object(entries));
+            } else {
+                bail!("Expected , or }} in object");
+            }
+        }
+    }
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        bail!("Invalid JSON at {}", s);
+    }
+    let number = s[..end].to_string();
+    *s = &s[end..];
+    Ok(Json::Number(number))
+}
+
+// Reads a transition table as written by `to_json`.
+pub fn parse_json(s: &str) -> Result<States, Error> {
+    let mut rest = s;
+    let json = parse_json_value(&mut rest)?;
+    ensure!(rest.trim().is_empty(), "Trailing text after JSON: {}", rest);
+    let mut table = Vec::new();
+    for (name, values) in json.into_object()? {
+        let mut entries = Vec::new();
+        for (value, transition) in values.into_object()? {
+            let transition = match transition {
+                Json::Null => None,
+                transition => {
+                    let mut fields = HashMap::new();
+                    for (key, field) in transition.into_object()? {
+                        fields.insert(key, field.into_text()?);
+                    }
+                    let field = |key| {
+                        fields
+                            .get(key)
+                            .ok_or_else(|| format_err!("No {} in state {}", key, name))
+                    };
+                    Some((
+                        field("write")?.parse()?,
+                        field("move")?.parse()?,
+                        state_name(field("next")?)?,
+                    ))
+                }
+            };
+            entries.push((value, transition));
+        }
+        table.push((state_name(&name)?, entries));
+    }
+    from_table(table)
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+// Reads a transition table as written by `to_yaml`: a mapping of states to
+// mappings of values read to `null` or a flow mapping of the transition.
+pub fn parse_yaml(s: &str) -> Result<States, Error> {
+    let mut table: Vec<(char, Vec<Entry>)> = Vec::new();
+    for line in s.lines().filter(|line| !line.trim().is_empty()) {
+        let (key, rest) = line
+            .split_once(':')
+            .ok_or_else(|| format_err!("Expected a key in {}", line))?;
+        if !line.starts_with(' ') {
+            ensure!(rest.trim().is_empty(), "Expected a state in {}", line);
+            table.push((state_name(unquote(key))?, Vec::new()));
+            continue;
+        }
+        let entries = &mut table
+            .last_mut()
+            .ok_or_else(|| format_err!("Value outside a state in {}", line))?
+            .1;
+        let rest = rest.trim();
+        if rest == "null" {
+            entries.push((unquote(key).to_string(), None));
+            continue;
+        }
+        let fields = rest
+            .strip_prefix('{')
+            .and_then(|rest| rest.strip_suffix('}'))
+            .ok_or_else(|| format_err!("Expected a transition in {}", line))?
+            .split(',')
+            .map(|field| {
+                field
+                    .split_once(':')
+                    .map(|(key, value)| (key.trim(), unquote(value)))
+                    .ok_or_else(|| format_err!("Invalid field {} in {}", field, line))
+            })
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+        let field = |key| {
+            fields
+                .get(key)
+                .cloned()
+                .ok_or_else(|| format_err!("No {} in {}", key, line))
+        };
+        entries.push((
+            unquote(key).to_string(),
+            Some((
+                field("write")?.parse()?,
+                field("move")?.parse()?,
+                state_name(field("next")?)?,
+            )),
+        ));
+    }
+    from_table(table)
+}
+
+// A Graphviz diagram with an edge per transition labelled `read/write move`,
+// states without blueprints drawn as halting ones and `start` marked.
+pub fn to_dot(states: &States, start: char) -> String {
+    let mut dot = String::from("digraph machine {\n  rankdir=LR;\n");
+    dot.push_str(&format!("  start [shape=point];\n  start -> {};\n", start));
+    let mut halting = Vec::new();
+    for (name, sub_states) in sorted(states) {
+        for (value, sub_state) in sub_states.iter().enumerate() {
+            if let Some(s) = sub_state {
+                dot.push_str(&format!(
+                    "  {} -> {} [label=\"{}/{} {}\"];\n",
+                    name,
+                    s.next_state,
+                    value,
+                    digit(s.write),
+                    letter(&s.movement)
+                ));
+                if s.halts && !halting.contains(&s.next_state) {
+                    halting.push(s.next_state);
+                }
+            }
+        }
+    }
+    for name in halting {
+        dot.push_str(&format!("  {} [shape=doublecircle];\n", name));
+    }
+    dot.push_str("}\n");
+    dot
+}
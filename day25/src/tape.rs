@@ -0,0 +1,61 @@
+// Words of tape added at least whenever it runs out, on either side.
+const CHUNK: usize = 64;
+
+// A tape of cells packed as bits, cell `i` being bit `i % 64` of word
+// `i / 64`, counting its ones as they are written.
+#[derive(Clone, Debug)]
+pub struct Tape {
+    words: Vec<u64>,
+    ones: usize,
+}
+
+impl Tape {
+    pub fn new() -> Tape {
+        Tape {
+            words: vec![0; CHUNK],
+            ones: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    pub fn ones(&self) -> usize {
+        self.ones
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        (self.words[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        let (word, bit) = (&mut self.words[i / 64], 1 << (i % 64));
+        if (*word & bit != 0) != value {
+            *word ^= bit;
+            if value {
+                self.ones += 1;
+            } else {
+                self.ones -= 1;
+            }
+        }
+    }
+
+    // How many words to add, doubling the tape so growing stays cheap.
+    fn growth(&self) -> usize {
+        self.words.len().max(CHUNK)
+    }
+
+    // Adds blank cells before the first one, returning how many so positions
+    // can be shifted along.
+    pub fn grow_left(&mut self) -> usize {
+        let words = self.growth();
+        self.words.splice(0..0, vec![0; words]);
+        words * 64
+    }
+
+    pub fn grow_right(&mut self) {
+        let words = self.growth();
+        self.words.resize(self.words.len() + words, 0);
+    }
+}